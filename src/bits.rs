@@ -0,0 +1,48 @@
+//! A small big-endian bit-field reader.
+//!
+//! Mode S field layouts are naturally described as "the next N bits are field X",
+//! which the hand-rolled shift/mask expressions scattered through `main.rs` make
+//! hard to read and easy to get wrong. `take_bits` extracts a single field; `BitReader`
+//! wraps it with a cursor so a message layout can be written as a straight-line
+//! sequence of calls instead of re-deriving each field's absolute bit offset by hand.
+
+/// Reads `nbits` (at most 64) starting at bit `offset` in `data`, MSB-first (bit 0
+/// is the high bit of `data[0]`).
+pub fn take_bits(data: &[u8], offset: u32, nbits: u32) -> u64 {
+    let mut value: u64 = 0;
+
+    for i in 0..nbits {
+        let bit_ndx = offset + i;
+        let byte = data[(bit_ndx / 8) as usize];
+        let bit = (byte >> (7 - (bit_ndx % 8))) & 1;
+        value = (value << 1) | bit as u64;
+    }
+
+    value
+}
+
+/// Reads fields out of a byte slice MSB-first, advancing its own bit cursor so a
+/// message layout reads as a sequence of `take_bits` calls.
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    offset: u32,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader { data, offset: 0 }
+    }
+
+    /// Reads `nbits` starting at the cursor and advances past them.
+    pub fn take_bits(&mut self, nbits: u32) -> u64 {
+        let value = take_bits(self.data, self.offset, nbits);
+        self.offset += nbits;
+        value
+    }
+
+    /// Advances the cursor past `nbits` without reading them, e.g. to step over a
+    /// reserved or spare field.
+    pub fn skip_bits(&mut self, nbits: u32) {
+        self.offset += nbits;
+    }
+}
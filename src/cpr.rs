@@ -138,7 +138,95 @@ pub fn decode_cpr(even: (u32, u32, u64), odd: (u32, u32, u64)) -> Option<(f32, f
         let lat = rlat1;
         if lon > 180.0 {
             lon -= 360.0;
-        }        
+        }
         Some((lat, lon))
     }
+}
+
+/// Decodes a matched even/odd pair of `SurfacePositionMessage` CPR frames.
+///
+/// Surface coordinates are encoded the same way as airborne ones, except the
+/// CPR grid only spans a 90 degree quadrant instead of the full 360, so
+/// `air_dlat0`/`air_dlat1` are a quarter of `decode_cpr`'s. Because the 90
+/// degree zone repeats across all four quadrants of the globe, the result is
+/// resolved against the receiver's reference position by adding whichever
+/// multiple of 90 degrees lands closest to `ref_lat`/`ref_lon`.
+pub fn decode_cpr_surface(
+    even: (u32, u32, u64),
+    odd: (u32, u32, u64),
+    ref_lat: f32,
+    ref_lon: f32
+) -> Option<(f32, f32)> {
+    let air_dlat0: f32 = 90.0 / 60.0;
+    let air_dlat1: f32 = 90.0 / 59.0;
+    let lat0 = even.0 as f32;
+    let lat1 = odd.0 as f32;
+    let lon0 = even.1 as f32;
+    let lon1 = odd.1 as f32;
+
+    let j = (((59.0 * lat0 - 60.0 * lat1) / 131072.0) + 0.5).floor();
+    let rlat0 = air_dlat0 * (cpr_mod_function(j, 60.0) + lat0 / 131072.0);
+    let rlat1 = air_dlat1 * (cpr_mod_function(j, 59.0) + lat1 / 131072.0);
+
+    if cpr_nl_function(rlat0) != cpr_nl_function(rlat1) {
+        return None;
+    }
+
+    let (mut rlat, mut rlon) = if even.2 > odd.2 {
+        let ni = cpr_n_function(rlat0, 0.0);
+        let m = ((((lon0 * (cpr_nl_function(rlat0) - 1.0)) - (lon1 * cpr_nl_function(rlat0))) / 131072.0) + 0.5).floor();
+        let lon = (90.0 / ni) * (cpr_mod_function(m, ni) + lon0 / 131072.0);
+        (rlat0, lon)
+    } else {
+        let ni = cpr_n_function(rlat1, 1.0);
+        let m = ((((lon0 * (cpr_nl_function(rlat1) - 1.0)) - (lon1 * cpr_nl_function(rlat1))) / 131072.0) + 0.5).floor();
+        let lon = (90.0 / ni) * (cpr_mod_function(m, ni) + lon1 / 131072.0);
+        (rlat1, lon)
+    };
+
+    // Move the decoded quadrant result to whichever of the four 90 degree
+    // zones is actually nearest the receiver.
+    rlat += ((ref_lat - rlat) / 90.0).round() * 90.0;
+    rlon += ((ref_lon - rlon) / 90.0).round() * 90.0;
+
+    Some((rlat, rlon))
+}
+
+/// Decodes a single airborne position frame relative to a known reference
+/// point (the station location, or a previously known aircraft position),
+/// instead of requiring a matched even/odd pair like `decode_cpr`.
+///
+/// `frame` is the raw `(lat_cpr, lon_cpr)` from the message and `odd`
+/// selects which CPR format (even/odd) it was encoded with. Returns `None`
+/// if the decoded latitude ends up more than ~3 degrees (~180 NM) from
+/// `ref_lat`, since the relative solution is only unambiguous that close
+/// to the reference point.
+pub fn decode_cpr_relative(frame: (u32, u32), odd: bool, ref_lat: f32, ref_lon: f32) -> Option<(f32, f32)> {
+    let lat_cpr = frame.0 as f32;
+    let lon_cpr = frame.1 as f32;
+
+    let dlat = if odd { 360.0 / 59.0 } else { 360.0 / 60.0 };
+
+    let j = (ref_lat / dlat).floor()
+        + (cpr_mod_function(ref_lat, dlat) / dlat - lat_cpr / 131072.0 + 0.5).floor();
+    let rlat = dlat * (j + lat_cpr / 131072.0);
+
+    if (rlat - ref_lat).abs() > 3.0 {
+        return None;
+    }
+
+    let nl = cpr_nl_function(rlat) - if odd { 1.0 } else { 0.0 };
+    let dlon = 360.0 / nl.max(1.0);
+
+    let m = (ref_lon / dlon).floor()
+        + (cpr_mod_function(ref_lon, dlon) / dlon - lon_cpr / 131072.0 + 0.5).floor();
+    let mut rlon = dlon * (m + lon_cpr / 131072.0);
+
+    if rlon > 180.0 {
+        rlon -= 360.0;
+    } else if rlon < -180.0 {
+        rlon += 360.0;
+    }
+
+    Some((rlat, rlon))
 }
\ No newline at end of file
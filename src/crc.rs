@@ -44,73 +44,464 @@ pub fn modes_compute_crc(msg: &[u8]) -> u32 {
     crc & 0xffffff
 }
 
+/// Feeds one byte through eight rounds of the Mode S CRC's shift-and-XOR step,
+/// `crc = (crc << 1) ^ (0xFFF409 if the top bit of the 24-bit register is set)`,
+/// starting with the byte in the register's top 8 bits. `MODES_CRC_BYTE_TABLE[b]`
+/// is this applied to byte `b`, which is all `modes_compute_crc_bytewise` needs to
+/// fold a whole byte into the running CRC in one step instead of walking its 8 bits.
+const fn crc_byte_table_entry(byte: u8) -> u32 {
+    let mut crc: u32 = (byte as u32) << 16;
+    let mut i = 0;
+    while i < 8 {
+        crc = if crc & 0x800000 != 0 {
+            (crc << 1) ^ 0xfff409
+        } else {
+            crc << 1
+        } & 0xffffff;
+        i += 1;
+    }
+    crc
+}
+
+const fn build_crc_byte_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut b = 0usize;
+    while b < 256 {
+        table[b] = crc_byte_table_entry(b as u8);
+        b += 1;
+    }
+    table
+}
+
+const MODES_CRC_BYTE_TABLE: [u32; 256] = build_crc_byte_table();
+
+/// Table-driven byte-wise computation of the Mode S CRC, producing the identical
+/// result as `modes_compute_crc`'s bit-wise loop but processing the message a byte
+/// at a time via `MODES_CRC_BYTE_TABLE` instead of walking every one of its bits
+/// (roughly 8x fewer iterations over a 112-bit frame). `msg`'s trailing 3 checksum
+/// bytes are excluded from the fold, same as `modes_compute_crc`.
+pub fn modes_compute_crc_bytewise(msg: &[u8]) -> u32 {
+    let mut crc: u32 = 0;
+
+    for &byte in &msg[..msg.len() - 3] {
+        crc = ((crc << 8) ^ MODES_CRC_BYTE_TABLE[(((crc >> 16) ^ byte as u32) & 0xff) as usize]) & 0xffffff;
+    }
+
+    crc
+}
+
+/// `BARRETT_MU = floor(x^48 / G)`, `G` being the 25-bit Mode S generator
+/// `0x1FFF409` (the same polynomial `MODES_CHECKSUM_TABLE`/`MODES_CRC_BYTE_TABLE`
+/// are built from). Computed at compile time by the standard GF(2) long-division
+/// algorithm: at each step, if the dividend's current top bit is set, that shift
+/// of the quotient is 1 and `G` shifted the same amount is subtracted (XORed) out.
+const fn compute_barrett_mu() -> u64 {
+    const G: u64 = 0x1fff409;
+
+    let mut rem: u64 = 1u64 << 48;
+    let mut quotient: u64 = 0;
+    let mut shift: i32 = 48 - 24;
+
+    while shift >= 0 {
+        if rem & (1u64 << (shift + 24)) != 0 {
+            quotient |= 1u64 << shift;
+            rem ^= G << shift;
+        }
+        shift -= 1;
+    }
+
+    quotient
+}
+
+const BARRETT_MU: u64 = compute_barrett_mu();
+const G_FULL: u64 = 0x1fff409;
+
+/// Carry-less multiply of two values, each assumed to fit within 25 bits (true of
+/// every operand `barrett_reduce` passes in), so the true GF(2) product always fits
+/// in the low 64 bits of `_mm_clmulepi64_si128`'s 128-bit result and the high half
+/// can be ignored.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "pclmulqdq")]
+unsafe fn clmul64(a: u64, b: u64) -> u64 {
+    use std::arch::x86_64::*;
+
+    let prod = _mm_clmulepi64_si128(_mm_set_epi64x(0, a as i64), _mm_set_epi64x(0, b as i64), 0x00);
+    _mm_cvtsi128_si64(prod) as u64
+}
+
+/// Barrett-reduces `d` (any value of degree at most 47, i.e. `d < 1 << 48`) modulo
+/// `G_FULL`, yielding the 24-bit remainder. Standard two-multiply Barrett scheme:
+/// `t1 = d >> 24` is `d`'s top half; `t2 = t1 * BARRETT_MU` estimates how many
+/// copies of `G` divide into `d`; `t3 = t2 >> 24` is that estimate's integer part;
+/// `t4 = t3 * G_FULL` is the corresponding multiple of `G` to subtract (XOR, in
+/// GF(2)) out of `d`, leaving the remainder in the low 24 bits.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "pclmulqdq")]
+unsafe fn barrett_reduce(d: u64) -> u32 {
+    let t1 = d >> 24;
+    let t2 = clmul64(t1, BARRETT_MU);
+    let t3 = t2 >> 24;
+    let t4 = clmul64(t3, G_FULL);
+    ((d ^ t4) & 0xffffff) as u32
+}
+
+/// PCLMULQDQ-accelerated Mode S CRC, folding the message in 3-byte (24-bit) chunks
+/// instead of `modes_compute_crc_bytewise`'s one-byte-at-a-time table lookups.
+///
+/// Each chunk is folded in by a plain shift-and-XOR (`combined = (crc << w) ^
+/// chunk`, `w` the chunk's width in bits) rather than a carry-less multiply: that
+/// step just multiplies the running remainder's polynomial by a power of `x`,
+/// which is exactly what a left shift does. Since a 24-bit `crc` folded against a
+/// 24-bit chunk never exceeds degree 47, a single Barrett reduction per chunk is
+/// enough to bring it back under 24 bits before the next fold, so the actual
+/// carry-less multiplication only happens inside `barrett_reduce`.
+///
+/// Frames are only 56 or 112 bits, so this is a handful of folds plus a Barrett
+/// step each, rather than the full 128-bit-chunk fold-then-reduce pipeline
+/// `crc-rs`-style SIMD CRCs use over much longer buffers; the win here comes from
+/// replacing the demod hot path's per-byte table lookups with a couple of CLMUL
+/// instructions. 64-bit aarch64 PMULL is not implemented here; non-x86_64 builds
+/// and x86_64 CPUs without PCLMULQDQ fall back to the bytewise table instead.
+///
+/// The Mode S CRC is defined over the message with its 24-bit checksum field
+/// (all zero here, since `msg`'s trailing 3 bytes are excluded) appended, i.e.
+/// `msg_bits * x^24 mod G`, not just `msg_bits mod G`. Folding `msg`'s data
+/// chunks only accounts for `msg_bits`, so one final fold of the running
+/// remainder shifted up by those 24 zero bits is still needed afterward to
+/// match `modes_compute_crc_bytewise`.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "pclmulqdq")]
+unsafe fn modes_compute_crc_pclmul(msg: &[u8]) -> u32 {
+    let mut crc: u64 = 0;
+
+    for chunk in msg[..msg.len() - 3].chunks(3) {
+        let mut chunk_val: u64 = 0;
+        for &byte in chunk {
+            chunk_val = (chunk_val << 8) | byte as u64;
+        }
+
+        let combined = (crc << (chunk.len() * 8)) ^ chunk_val;
+        crc = barrett_reduce(combined) as u64;
+    }
+
+    crc = barrett_reduce(crc << 24) as u64;
+
+    crc as u32
+}
+
+/// Dispatches to the PCLMULQDQ-accelerated CRC fold when the CPU supports it,
+/// falling back to the portable byte-table version otherwise.
+pub fn modes_compute_crc_fast(msg: &[u8]) -> u32 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("pclmulqdq") {
+            return unsafe { modes_compute_crc_pclmul(msg) };
+        }
+    }
+
+    modes_compute_crc_bytewise(msg)
+}
+
 pub fn modes_checksum(msg: &[u8]) -> u32 {
-    let crc = modes_compute_crc(msg);
+    let crc = modes_compute_crc_fast(msg);
     let sz = msg.len();
     let rem = ((msg[sz - 3] as u32) << 16) | ((msg[sz - 2] as u32) << 8) | msg[sz - 1] as u32;
     return (crc ^ rem) & 0xffffff;
 }
 
-pub fn modes_init_error_info() -> HashMap<u32, u16> {
+/// Whether `msgtype` (a downlink format) is an Address/Parity format: DF 0, 4, 5,
+/// 16, 20, and 21, plus DF24/Comm-D, none of which carry an explicit ICAO address
+/// field. Unlike DF11/17/18, which carry the address in the clear and a
+/// stand-alone parity field, these overlay the two: the embedded 24-bit field is
+/// `address ^ true_crc` rather than just `true_crc`, so `modes_checksum`'s usual
+/// "residual is zero" validity check doesn't apply and the address has to be
+/// recovered from the residual instead (`recover_icao_address`).
+pub fn is_address_parity_df(msgtype: u8) -> bool {
+    matches!(msgtype, 0 | 4 | 5 | 16 | 20 | 21 | 24)
+}
+
+/// Recovers the candidate ICAO address embedded in an Address/Parity frame (see
+/// `is_address_parity_df`) via its CRC residual.
+///
+/// For these formats `msg`'s trailing 3 bytes are `address ^ true_crc` rather
+/// than a stand-alone checksum, so `modes_checksum` - which folds the computed
+/// CRC against those bytes - yields `computed_crc ^ address ^ true_crc`. When
+/// `msg` has no bit errors, `computed_crc == true_crc` and the two cancel,
+/// leaving the address outright: recovery is the exact same residual the
+/// DF11/17 "CRC OK" check already computes, just read as an address instead of
+/// as a zero/nonzero validity flag. The caller is responsible for only trusting
+/// the result against a known-address table (see `brute_force_ap` in main.rs),
+/// since a garbled frame recovers a garbage "address" just as readily.
+pub fn recover_icao_address(msg: &[u8]) -> u32 {
+    modes_checksum(msg)
+}
+
+/// Incremental Mode S checksum, for callers that receive a frame's bytes a piece
+/// at a time (a demodulator, a socket) instead of having the whole message slice
+/// up front. `modes_compute_crc_bytewise`/`modes_checksum` can't be fed a partial
+/// message directly because the trailing 3 bytes are the embedded checksum, not
+/// part of the fold, and that boundary isn't known until the message ends.
+///
+/// `ModeSDigest` handles this by delaying the fold by 3 bytes: `pending` buffers
+/// the most recent (up to) 3 bytes that haven't been folded into `crc` yet, since
+/// they might turn out to be the trailing checksum. Every `update` that pushes
+/// `pending` past 3 bytes folds the oldest one out of it and into `crc` via
+/// `MODES_CRC_BYTE_TABLE`, the same table `modes_compute_crc_bytewise` uses.
+/// `finalize` XORs the embedded checksum out of whatever 3 bytes are left in
+/// `pending`, mirroring `modes_checksum`'s `crc ^ rem`.
+pub struct ModeSDigest {
+    crc: u32,
+    pending: std::collections::VecDeque<u8>,
+}
+
+impl ModeSDigest {
+    pub fn new() -> ModeSDigest {
+        ModeSDigest {
+            crc: 0,
+            pending: std::collections::VecDeque::with_capacity(3),
+        }
+    }
+
+    pub fn update(&mut self, buf: &[u8]) {
+        for &byte in buf {
+            if self.pending.len() == 3 {
+                let oldest = self.pending.pop_front().unwrap();
+                self.crc = ((self.crc << 8) ^ MODES_CRC_BYTE_TABLE[(((self.crc >> 16) ^ oldest as u32) & 0xff) as usize]) & 0xffffff;
+            }
+            self.pending.push_back(byte);
+        }
+    }
+
+    /// Consumes the digest, returning the checksum residual the way
+    /// `modes_checksum` does: the running CRC over every byte but the last 3,
+    /// XORed with the embedded checksum those last 3 bytes carry.
+    ///
+    /// `pending` must hold exactly the 3 trailing bytes at this point; a digest
+    /// fed fewer than 3 total bytes has no embedded checksum to XOR against and
+    /// this panics, same as indexing `msg[sz - 3..]` on a too-short slice would in
+    /// `modes_checksum`.
+    pub fn finalize(self) -> u32 {
+        assert_eq!(self.pending.len(), 3, "ModeSDigest::finalize: fewer than 3 bytes were written");
+
+        let rem = ((self.pending[0] as u32) << 16) | ((self.pending[1] as u32) << 8) | self.pending[2] as u32;
+        (self.crc ^ rem) & 0xffffff
+    }
+}
+
+impl std::io::Write for ModeSDigest {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Packs up to 3 flipped bit positions into one syndrome-table value, one byte
+/// per position (`positions[0] | positions[1] << 8 | positions[2] << 16`),
+/// widened from the old `u16`'s 2-position `a | b << 8` so a 3rd position fits.
+/// Position 0 is never a real flipped bit (the loops below start at bit 5), so an
+/// all-zero byte still doubles as "this slot isn't used", same as `b == 0` meant
+/// "no second bit" in the old encoding.
+fn encode_positions(positions: &[usize]) -> u32 {
+    let mut encoded: u32 = 0;
+    for (slot, &pos) in positions.iter().enumerate() {
+        encoded |= (pos as u32) << (slot * 8);
+    }
+    encoded
+}
+
+/// Recursively extends `positions` with every combination of `remaining` more
+/// bit indices greater than the last one chosen, flipping each bit in `msg` as
+/// it's picked, recording the resulting checksum in `table`, recursing for
+/// deeper combinations, then unflipping as it backtracks to try the next one.
+///
+/// This generalizes `modes_init_error_info`'s old fixed pair of nested loops
+/// (one for single-bit errors, one nested inside it for pairs) to an arbitrary
+/// depth: a table entry is recorded after every bit flipped at every depth, not
+/// just at the deepest one, matching the old code recording both the 1-bit and
+/// 2-bit syndromes.
+fn generate_error_combinations(
+    msg: &mut [u8],
+    start: usize,
+    remaining: usize,
+    positions: &mut Vec<usize>,
+    table: &mut HashMap<u32, u32>,
+) {
+    for i in start..constants::MODES_LONG_MSG_BITS {
+        let bytepos = i >> 3;
+        let mask = 1 << (7 - (i & 7));
+        msg[bytepos] ^= mask;
+        positions.push(i);
+
+        let syndrome = modes_checksum(msg);
+        table.insert(syndrome, encode_positions(positions));
+
+        if remaining > 1 {
+            generate_error_combinations(msg, i + 1, remaining - 1, positions, table);
+        }
+
+        positions.pop();
+        msg[bytepos] ^= mask;
+    }
+}
+
+/// Builds the Mode S bit-error (syndrome) correction table out to `max_depth`
+/// simultaneously flipped bits.
+///
+/// Table size grows combinatorially with depth: depth 1 stores one entry per bit
+/// position (~107 for a 112-bit frame, since the loops start at bit 5), depth 2
+/// adds roughly C(107, 2) ≈ 5,700 more pair entries, and depth 3 adds roughly
+/// C(107, 3) ≈ 198,000 more triple entries on top of that. Depth 1-2 (the
+/// default `modes_init_error_info` builds) is cheap; depth 3 is available for
+/// very noisy RF environments, but callers should budget for its much larger
+/// table and slower one-time build.
+pub fn modes_init_error_info_depth(max_depth: usize) -> HashMap<u32, u32> {
     let mut msg: Vec<u8> = vec![0; constants::MODES_LONG_MSG_BYTES];
-    let mut bit_error_table = HashMap::new();
+    let mut table = HashMap::new();
+    let mut positions: Vec<usize> = Vec::with_capacity(max_depth);
 
-    for i in 5..constants::MODES_LONG_MSG_BITS {
-        let bytepos0: usize = i >> 3;
-        let mask0: u8 = 1 << (7 - (i & 7));
-        msg[bytepos0] = msg[bytepos0] & mask0;
-        let crc0 = modes_checksum(&msg);
+    if max_depth > 0 {
+        generate_error_combinations(&mut msg, 5, max_depth, &mut positions, &mut table);
+    }
 
-        bit_error_table.insert(crc0, i as u16);
+    table
+}
+
+static DEFAULT_BIT_ERROR_TABLE: std::sync::OnceLock<HashMap<u32, u32>> = std::sync::OnceLock::new();
 
-        for j in i + 1..constants::MODES_LONG_MSG_BITS {
-            let bytepos1: usize = j >> 3;
-            let mask1: u8 = 1 << (7 - (j & 7));
-            msg[bytepos1] = msg[bytepos1] ^ mask1;
-            let crc1 = modes_checksum(&msg);
+/// The shared depth-2 (1- and 2-bit) syndrome table, built once on first call
+/// and cached behind a `OnceLock` instead of rebuilt by every caller, which is
+/// what this used to do (an O(bits^2) scan allocating a fresh `HashMap` every
+/// time). Opt into 3-bit correction via `modes_init_error_info_depth(3)`
+/// directly, since its table is large enough (see that function's doc comment)
+/// that it shouldn't be built implicitly just by calling this.
+pub fn modes_init_error_info() -> &'static HashMap<u32, u32> {
+    DEFAULT_BIT_ERROR_TABLE.get_or_init(|| modes_init_error_info_depth(2))
+}
 
-            bit_error_table.insert(crc1, i as u16 | ((j as u16) << 8));
+static DEPTH3_BIT_ERROR_TABLE: std::sync::OnceLock<HashMap<u32, u32>> = std::sync::OnceLock::new();
 
-            msg[bytepos1] = msg[bytepos1] & mask1;
+/// Returns the cached depth-2 table by default, or the cached depth-3 table when
+/// `depth` is 3 or more, so a CLI-selected correction depth doesn't have to
+/// thread a one-off `HashMap` through every call site - each depth is still
+/// only ever built once, behind its own `OnceLock`, no matter how many workers
+/// ask for it.
+pub fn modes_init_error_info_for_depth(depth: usize) -> &'static HashMap<u32, u32> {
+    if depth >= 3 {
+        DEPTH3_BIT_ERROR_TABLE.get_or_init(|| modes_init_error_info_depth(3))
+    } else {
+        modes_init_error_info()
+    }
+}
+
+/// Applies the bit flips `encoded` (one of `bit_error_table`'s values) records,
+/// returning how many were applied (0 if any position doesn't fit `msg`'s
+/// actual length). Positions are validated against `offset` up front, before
+/// any bits are flipped, so a record that doesn't fit this (possibly
+/// truncated) message leaves `msg` untouched.
+fn apply_bit_flip_positions(msg: &mut [u8], encoded: u32, offset: usize) -> u8 {
+    let mut positions: Vec<usize> = Vec::with_capacity(3);
+    for slot in 0..3 {
+        let pos = ((encoded >> (slot * 8)) & 0xff) as usize;
+        if pos == 0 {
+            break;
+        }
+        if offset > pos {
+            return 0;
         }
+        positions.push(pos);
+    }
 
-        msg[bytepos0] = msg[bytepos0] & mask0;
+    for &pos in &positions {
+        let bitpos = pos - offset;
+        msg[bitpos >> 3] ^= 1 << (7 - (bitpos & 7));
     }
 
-    bit_error_table
+    positions.len() as u8
 }
 
-pub fn fix_bit_errors(msg: &mut [u8], bit_error_table: &HashMap<u32, u16>) -> u8 {
+/// Applies the bit flips recorded against `msg`'s CRC syndrome in
+/// `bit_error_table`, returning how many bits were corrected (0 if the syndrome
+/// isn't in the table). Handles however many positions the matched entry
+/// records (1, 2, or 3 with the default depth-2/opt-in depth-3 tables), rather
+/// than being hard-wired to at most two.
+pub fn fix_bit_errors(msg: &mut [u8], bit_error_table: &HashMap<u32, u32>) -> u8 {
     let syndrome = modes_checksum(msg);
     let offset: usize = constants::MODES_LONG_MSG_BITS - msg.len() * 8;
+
     match bit_error_table.get(&syndrome) {
-        Some(pei) => {
-            let a = (pei & 0xff) as usize;
-            let b = ((pei >> 8) & 0xff) as usize;
-
-            if b != 0 {
-                if offset > a {
-                    return 0;
-                }
-                
-                if offset > b {
-                    return 0;
-                }
-
-                let bitpos0 = a - offset;
-                let bitpos1 = b - offset;
-                msg[bitpos0 >> 3] = msg[bitpos0 >> 3] ^ (1 << (7 - (bitpos0 & 7)));
-                msg[bitpos1 >> 3] = msg[bitpos1 >> 3] ^ (1 << (7 - (bitpos1 & 7)));
-                2
-            } else {
-                if offset > a {
-                    return 0;
-                }
-                let bitpos0 = a - offset;
-                msg[bitpos0 >> 3] = msg[bitpos0 >> 3] ^ (1 << (7 - (bitpos0 & 7)));
-                1
-            }
-        },
+        Some(&encoded) => apply_bit_flip_positions(msg, encoded, offset),
         None => 0,
     }
-}
\ No newline at end of file
+}
+
+/// Corrects an Address/Parity frame (see `is_address_parity_df`) whose embedded
+/// `address ^ true_crc` residual doesn't directly match any recently-seen
+/// address, by searching for a bit-error pattern that would explain the
+/// mismatch.
+///
+/// The Mode S CRC is linear, so a frame with bit errors at a recorded pattern
+/// `E` recovers `recover_icao_address(msg) == true_address ^ delta(E)`, where
+/// `delta(E)` is exactly the syndrome `bit_error_table` records for `E` -
+/// flipping `E`'s positions changes the checksum by the same amount
+/// regardless of the rest of the message. Running `fix_bit_errors` directly on
+/// an AP frame doesn't work (its lookup key is `delta(E)` alone, but an AP
+/// frame's residual is `delta(E) ^ true_address`, essentially never a real
+/// table entry by coincidence); instead, for each `candidate_addr` already
+/// known to be active, this checks whether `recover_icao_address(msg) ^
+/// candidate_addr` is itself a recorded error pattern. If so, that pattern is
+/// applied to `msg` and `candidate_addr` is returned.
+pub fn fix_ap_bit_errors<'a>(
+    msg: &mut [u8],
+    bit_error_table: &HashMap<u32, u32>,
+    candidate_addrs: impl Iterator<Item = &'a u32>,
+) -> Option<u32> {
+    let raw_addr = recover_icao_address(msg);
+    let offset: usize = constants::MODES_LONG_MSG_BITS - msg.len() * 8;
+
+    for &candidate_addr in candidate_addrs {
+        let delta = raw_addr ^ candidate_addr;
+
+        if delta == 0 {
+            continue;
+        }
+
+        if let Some(&encoded) = bit_error_table.get(&delta) {
+            let mut candidate = msg.to_vec();
+            if apply_bit_flip_positions(&mut candidate, encoded, offset) == 0 {
+                continue;
+            }
+
+            if recover_icao_address(&candidate) == candidate_addr {
+                msg.copy_from_slice(&candidate);
+                return Some(candidate_addr);
+            }
+        }
+    }
+
+    None
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn fast_crc_matches_bytewise_over_random_buffers() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..1000 {
+            for &len in &[7usize, 14usize] {
+                let msg: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+                assert_eq!(
+                    modes_compute_crc_fast(&msg),
+                    modes_compute_crc_bytewise(&msg),
+                    "mismatch for {}-byte buffer {:?}", len, msg
+                );
+            }
+        }
+    }
+}
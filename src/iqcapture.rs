@@ -0,0 +1,136 @@
+//! Raw I/Q buffer record-and-replay harness for regression-testing `process_buffer`.
+//!
+//! `capture.rs` records already-decoded `Message`s, which is fine for replaying
+//! tracking/output changes but can't exercise the beamforming or preamble-detection
+//! code itself, since by the time a `Message` exists that work is already done. This
+//! module instead records the raw `u8_buffer` handed to `stream::process_buffer`,
+//! plus the `streams`/`base_pipe_ndx`/per-pipe theta and amplitude configuration it
+//! was called with, so a fixture recording can be fed back through the exact same
+//! entry point. `IqCaptureReader::read_buffer` returns the whole recording as one
+//! buffer, and pipes without a recorded theta draw new random thetas on replay, so
+//! the replayed `Vec<Message>` is not guaranteed to equal the live one.
+//!
+//! The interleaved int16 I/Q itself is stored as a multi-channel WAV (`channels =
+//! streams * 2`) via the `hound` crate, so a capture can also be opened in ordinary
+//! audio/DSP tools for inspection. The `streams`/`base_pipe_ndx`/theta/amplitude
+//! configuration doesn't fit in a WAV header, so it's written to a small JSON
+//! sidecar file alongside it (`<path>.json`).
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+
+use bytemuck::cast_slice;
+use serde::{Deserialize, Serialize};
+
+/// The sidecar header recorded next to an I/Q capture's WAV file.
+#[derive(Serialize, Deserialize)]
+struct IqCaptureHeader {
+    streams: usize,
+    base_pipe_ndx: usize,
+    pipe_theta: Vec<Option<Vec<f32>>>,
+    pipe_amps: Vec<Option<Vec<f32>>>,
+}
+
+fn sidecar_path(path: &str) -> String {
+    format!("{}.json", path)
+}
+
+fn io_err<E: std::fmt::Display>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// Writes raw `process_buffer` input buffers to a multi-channel WAV, alongside a
+/// JSON sidecar recording the `streams`/`base_pipe_ndx`/pipe theta-amplitude
+/// configuration the capture was made under.
+///
+/// The configuration is recorded once, at `create` time; if a caller reassigns a
+/// pipe's theta/amplitude partway through a capture, only the configuration in
+/// effect when the writer was created is preserved. That's the expected use: a
+/// capture is a short, fixed-configuration fixture recording, not a log of every
+/// configuration change.
+pub struct IqCaptureWriter {
+    writer: hound::WavWriter<BufWriter<File>>,
+}
+
+impl IqCaptureWriter {
+    pub fn create(
+        path: &str,
+        sample_rate: u32,
+        streams: usize,
+        base_pipe_ndx: usize,
+        pipe_theta: &Vec<Option<Vec<f32>>>,
+        pipe_amps: &Vec<Option<Vec<f32>>>,
+    ) -> io::Result<IqCaptureWriter> {
+        let spec = hound::WavSpec {
+            channels: (streams * 2) as u16,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+
+        let writer = hound::WavWriter::create(path, spec).map_err(io_err)?;
+
+        let header = IqCaptureHeader {
+            streams,
+            base_pipe_ndx,
+            pipe_theta: pipe_theta.clone(),
+            pipe_amps: pipe_amps.clone(),
+        };
+
+        let header_file = File::create(sidecar_path(path))?;
+        serde_json::to_writer(header_file, &header).map_err(io_err)?;
+
+        Ok(IqCaptureWriter { writer })
+    }
+
+    /// Appends one `u8_buffer`, as passed to `stream::process_buffer`, as interleaved
+    /// int16 samples across all `streams * 2` channels.
+    pub fn write_buffer(&mut self, u8_buffer: &[u8]) -> io::Result<()> {
+        let samples: &[i16] = cast_slice(u8_buffer);
+        for &sample in samples {
+            self.writer.write_sample(sample).map_err(io_err)?;
+        }
+        Ok(())
+    }
+
+    pub fn finalize(self) -> io::Result<()> {
+        self.writer.finalize().map_err(io_err)
+    }
+}
+
+/// Reads an I/Q capture written by `IqCaptureWriter` back into a `u8_buffer`/pipe
+/// configuration suitable for feeding straight into `stream::process_buffer`.
+pub struct IqCaptureReader {
+    reader: hound::WavReader<BufReader<File>>,
+    pub streams: usize,
+    pub base_pipe_ndx: usize,
+    pub pipe_theta: Vec<Option<Vec<f32>>>,
+    pub pipe_amps: Vec<Option<Vec<f32>>>,
+}
+
+impl IqCaptureReader {
+    pub fn open(path: &str) -> io::Result<IqCaptureReader> {
+        let header_file = File::open(sidecar_path(path))?;
+        let header: IqCaptureHeader = serde_json::from_reader(header_file).map_err(io_err)?;
+
+        let reader = hound::WavReader::open(path).map_err(io_err)?;
+
+        Ok(IqCaptureReader {
+            reader,
+            streams: header.streams,
+            base_pipe_ndx: header.base_pipe_ndx,
+            pipe_theta: header.pipe_theta,
+            pipe_amps: header.pipe_amps,
+        })
+    }
+
+    /// Reads the whole recording back into a `u8_buffer` suitable for
+    /// `stream::process_buffer`.
+    pub fn read_buffer(&mut self) -> io::Result<Vec<u8>> {
+        let samples: Vec<i16> = self.reader.samples::<i16>()
+            .collect::<Result<Vec<i16>, _>>()
+            .map_err(io_err)?;
+
+        Ok(cast_slice(&samples).to_vec())
+    }
+}
@@ -11,28 +11,38 @@
 //! Thanks to <https://github.com/flightaware/dump1090>
 
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::io::Read;
 use std::net::TcpStream;
-use bytemuck::bytes_of;
 use std::time::{Duration, Instant};
 use std::thread;
-use std::sync::mpsc::{channel, Sender, Receiver};
-use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{channel, Sender, Receiver, RecvTimeoutError};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::result::Result;
 use clap::Parser;
-use std::fs::File;
 use std::io::prelude::*;
 use std::fmt;
 use std::f32::consts::PI;
+use serde::{Serialize, Deserialize};
 
 mod crc;
 mod constants;
 mod stream;
 mod pipemgmt;
 mod cpr;
-
-use pipemgmt::{ThreadTxMessage, PipeManagement};
+mod netout;
+mod netin;
+mod bits;
+mod mvdr;
+mod notch;
+mod capture;
+mod iqcapture;
+mod recycler;
+
+use pipemgmt::{ThreadTxMessage, ThreadRxMessage, PipeReport, PipeManagement};
 use cpr::decode_cpr;
+use netout::NetOutput;
+use netin::UpstreamFormat;
 
 use constants::*;
 
@@ -43,6 +53,7 @@ use constants::*;
 /// message bytes, raw samples from the card, theta used to process the
 /// samples, amplitudes of each antenna, and if the CRC was okay for the
 /// message.
+#[derive(Serialize, Deserialize, Clone)]
 #[allow(dead_code)]
 struct MessageCommon {
     /// The bytes that comprise the message after demodulation.
@@ -59,6 +70,11 @@ struct MessageCommon {
     amplitudes: Vec<f32>,
     /// Was the CRC OK?
     crc_ok: bool,
+    /// How many bits `crc::fix_bit_errors` had to flip to make the CRC check out, so
+    /// consumers can weight confidence accordingly: 0 means `fix_bit_errors` flipped
+    /// nothing (an Address/Parity frame repaired by `crc::fix_ap_bit_errors` also
+    /// reports 0), and 2 or more only happens when `--aggressive-crc-fix` is enabled.
+    nfixed: u8,
     /// The global pipe index.
     pipe_ndx: usize,
 }
@@ -71,7 +87,7 @@ impl fmt::Debug for MessageCommon {
 }
 
 /// Represents a message after demodulation and decoding.
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 struct Message {
     common: MessageCommon,
     /// Any data specific to this message. For example, this
@@ -80,7 +96,7 @@ struct Message {
 }
 
 /// Elements that are common to a few different specific message types.
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[allow(dead_code)]
 struct DfHeader1 {
     capability: u8,
@@ -96,7 +112,7 @@ struct DfHeader1 {
 /// This is a good place to put anything specific if any
 /// decoding was done on the message. You could put fields
 /// specific to each message type here.
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 enum MessageSpecific {
     #[allow(dead_code)]
     AircraftIdenAndCat {
@@ -141,6 +157,78 @@ enum MessageSpecific {
         hdr: DfHeader1,
         heading: f32,
     },
+    /// DF0: short air-air surveillance (ACAS).
+    #[allow(dead_code)]
+    ShortAirToAirSurveillance {
+        hdr: DfHeader1,
+        vertical_status: u8,
+        altitude: f32,
+    },
+    /// DF4: surveillance altitude reply.
+    #[allow(dead_code)]
+    SurveillanceAltitudeReply {
+        hdr: DfHeader1,
+        altitude: f32,
+    },
+    /// DF5: surveillance identity reply. The squawk itself is `hdr.identity`.
+    #[allow(dead_code)]
+    SurveillanceIdentityReply {
+        hdr: DfHeader1,
+    },
+    /// DF16: long air-air surveillance (ACAS RA broadcast), altitude plus the raw
+    /// 56-bit MV field.
+    #[allow(dead_code)]
+    LongAirToAirSurveillance {
+        hdr: DfHeader1,
+        altitude: f32,
+        mv: Vec<u8>,
+    },
+    /// DF20: Comm-B altitude reply, altitude plus the raw 56-bit MB field.
+    #[allow(dead_code)]
+    CommBAltitudeReply {
+        hdr: DfHeader1,
+        altitude: f32,
+        mb: Vec<u8>,
+    },
+    /// DF21: Comm-B identity reply. The squawk is `hdr.identity`; `mb` is the raw
+    /// 56-bit Comm-B field.
+    #[allow(dead_code)]
+    CommBIdentityReply {
+        hdr: DfHeader1,
+        mb: Vec<u8>,
+    },
+    /// Extended squitter TYPE 28: aircraft status (emergency/priority state and the
+    /// Mode A code broadcast alongside it).
+    #[allow(dead_code)]
+    AircraftStatus {
+        hdr: DfHeader1,
+        emergency_state: u8,
+        squawk: u32,
+    },
+    /// Extended squitter TYPE 29: target state and status. Field widths/scaling
+    /// follow the same "close enough to be useful" approximation as the rest of
+    /// this file's decoding (see `decode_ac12_field`), not a bit-exact DO-260B
+    /// implementation.
+    #[allow(dead_code)]
+    TargetStateAndStatus {
+        hdr: DfHeader1,
+        selected_altitude: f32,
+        barometric_setting: f32,
+        selected_heading: f32,
+        autopilot_engaged: bool,
+        vnav_engaged: bool,
+        altitude_hold_engaged: bool,
+        lnav_engaged: bool,
+    },
+    /// Extended squitter TYPE 31: aircraft operational status.
+    #[allow(dead_code)]
+    OperationalStatus {
+        hdr: DfHeader1,
+        version: u8,
+        nic_supplement: u8,
+        nac_p: u8,
+        sil: u8,
+    },
     Other,
 }
 
@@ -159,6 +247,116 @@ fn decode_ac12_field(msg: &[u8]) -> f32 {
     }
 }
 
+/// The AC13 altitude field used by DF0/4/16/20 (bits 20-32 of the message). Same
+/// Q-bit/25ft-unit shape as `decode_ac12_field`, just extracted with `bits::take_bits`
+/// instead of a hand-rolled shift since it isn't byte-aligned the way AC12 is. The
+/// M bit (0x40 of the field) is stripped along with Q before the 25 ft scaling.
+fn decode_ac13_field(msg: &[u8]) -> f32 {
+    let raw = bits::take_bits(msg, 19, 13) as u32;
+    let q_bit = (raw >> 4) & 1;
+
+    if q_bit == 1 {
+        let n = ((raw & 0x1f80) >> 2) | ((raw & 0x0020) >> 1) | (raw & 0x000f);
+        n as f32 * 25.0 - 1000.0
+    } else {
+        0.0f32
+    }
+}
+
+/// Decodes a `SurfacePositionMessage`'s `movement` field into ground speed in
+/// knots, per the standard non-linear Mode S table. `0` means the speed is
+/// unknown (no reading); everything else is a piecewise-linear ramp that
+/// gets coarser as the speed increases.
+fn movement_to_knots(movement: u8) -> Option<f32> {
+    let knots = if movement == 0 {
+        return None;
+    } else if movement == 1 {
+        0.0
+    } else if movement <= 8 {
+        (movement - 1) as f32 * 0.125
+    } else if movement <= 12 {
+        1.0 + (movement - 9) as f32 * 0.25
+    } else if movement <= 38 {
+        2.0 + (movement - 13) as f32 * 0.5
+    } else if movement <= 93 {
+        15.0 + (movement - 39) as f32 * 1.0
+    } else if movement <= 108 {
+        70.0 + (movement - 94) as f32 * 2.0
+    } else if movement <= 123 {
+        100.0 + (movement - 109) as f32 * 5.0
+    } else {
+        175.0
+    };
+
+    Some(knots)
+}
+
+/// Decodes a `SurfacePositionMessage`'s `ground_track` field into degrees.
+fn ground_track_to_degrees(ground_track: u8) -> f32 {
+    ground_track as f32 * 360.0 / 128.0
+}
+
+/// Estimates a steering vector directly from the raw interleaved I/Q `samples` of a
+/// validated message window, instead of scanning many random-theta pipes and keeping
+/// whichever one happens to get the best SNR.
+///
+/// For each non-reference antenna `s` this computes the complex cross-correlation
+/// `C_s = sum_n A[n] * conj(S_s[n])` against the reference antenna (antenna 0) over
+/// the message window. `theta_s = arg(C_s)` is the phase rotation that coherently
+/// aligns antenna `s` with the reference, and `|C_s| / sum |A[n]|^2` is its amplitude
+/// relative to the reference. Returns `None` if `samples` isn't a whole number of
+/// `streams`-antenna complex frames.
+fn estimate_steering_vector(samples: &[i16], streams: usize) -> Option<(Vec<f32>, Vec<f32>)> {
+    if streams < 2 {
+        return None;
+    }
+
+    let frame_len = streams * 2;
+    if samples.len() % frame_len != 0 {
+        return None;
+    }
+
+    let frames = samples.len() / frame_len;
+    if frames == 0 {
+        return None;
+    }
+
+    let mut ref_energy = 0.0f32;
+    let mut cross: Vec<(f32, f32)> = vec![(0.0, 0.0); streams - 1];
+
+    for n in 0..frames {
+        let base = n * frame_len;
+        let ai = samples[base] as f32;
+        let aq = samples[base + 1] as f32;
+
+        ref_energy += ai * ai + aq * aq;
+
+        for s in 1..streams {
+            let bi = samples[base + s * 2] as f32;
+            let bq = samples[base + s * 2 + 1] as f32;
+
+            // A[n] * conj(S_s[n]) = (ai + i*aq)(bi - i*bq)
+            cross[s - 1].0 += ai * bi + aq * bq;
+            cross[s - 1].1 += aq * bi - ai * bq;
+        }
+    }
+
+    if ref_energy <= 0.0 {
+        return None;
+    }
+
+    let mut thetas: Vec<f32> = Vec::with_capacity(streams - 1);
+    let mut amplitudes: Vec<f32> = Vec::with_capacity(streams);
+    amplitudes.push(1.0);
+
+    for (ci, cq) in cross {
+        thetas.push(cq.atan2(ci));
+        amplitudes.push((ci * ci + cq * cq).sqrt() / ref_energy);
+    }
+
+    Some((thetas, amplitudes))
+}
+
 fn was_addr_recently_seen(addr: &u32, seen: &Arc<Mutex<HashMap<u32, Instant>>>) -> bool {
     match seen.lock().unwrap().get(addr) {
         Some(time_seen) => {
@@ -173,31 +371,53 @@ fn was_addr_recently_seen(addr: &u32, seen: &Arc<Mutex<HashMap<u32, Instant>>>)
     }
 }
 
-fn brute_force_ap(msg: &[u8], seen: &Arc<Mutex<HashMap<u32, Instant>>>) -> bool {
+/// For Address/Parity downlink formats (DF 0/4/5/16/20/21, and DF24/Comm-D which
+/// overlays AP the same way) there's no explicit address field to check against
+/// a recently-seen aircraft - `crc::recover_icao_address` has to recover a
+/// candidate from the CRC residual instead, and the only way to tell a real
+/// frame from noise is whether that candidate matches a known address.
+///
+/// Tries the residual as received first; if that doesn't match a recently-seen
+/// address, tries `crc::fix_ap_bit_errors` against every recently-seen address
+/// as a candidate true address, so a frame with a bit error the shared
+/// syndrome table covers still resolves to its real sender instead of being
+/// dropped outright - `crc::fix_bit_errors` can't be used directly here since
+/// an AP frame's residual is `address ^ delta(error)`, not a bare error
+/// syndrome (see `crc::fix_ap_bit_errors`'s doc comment). `msg` is only
+/// overwritten with the corrected bytes once a candidate address is actually
+/// confirmed.
+///
+/// Returns the confirmed ICAO address on success. AP formats carry no explicit
+/// AA field the way DF11/17/18 do, so this recovered address - not the raw
+/// first three data bytes - is the only valid `hdr.addr` for these frames.
+fn brute_force_ap(msg: &mut [u8], bit_error_table: &HashMap<u32, u32>, seen: &Arc<Mutex<HashMap<u32, Instant>>>) -> Option<u32> {
     let msgtype = msg[0] >> 3;
 
-    if
-        msgtype == 0 || msgtype == 4 || msgtype == 5 || 
-        msgtype == 16 || msgtype == 20 || msgtype == 21 || 
-        msgtype == 24
-    {
-        let crc = crc::modes_compute_crc(msg);
-        let last_byte = msg.len() - 1;
-        let aux0 = msg[last_byte - 0] as u32 ^ (crc & 0xff);
-        let aux1 = msg[last_byte - 1] as u32 ^ ((crc >> 8) & 0xff);
-        let aux2 = msg[last_byte - 2] as u32 ^ ((crc >> 16) & 0xff);
-        let addr = aux0 | (aux1 << 8) | (aux2 << 16);
-        was_addr_recently_seen(&addr, seen)
-    } else {
-        false
+    if !crc::is_address_parity_df(msgtype) {
+        return None;
+    }
+
+    let addr = crc::recover_icao_address(msg);
+    if was_addr_recently_seen(&addr, seen) {
+        return Some(addr);
     }
+
+    // Only addresses `was_addr_recently_seen` would itself still accept are
+    // worth trying as a candidate true address, same 60s window and all.
+    let candidate_addrs: Vec<u32> = seen.lock().unwrap()
+        .iter()
+        .filter(|(_, time_seen)| (Instant::now() - **time_seen).as_secs() < 60)
+        .map(|(addr, _)| *addr)
+        .collect();
+    crc::fix_ap_bit_errors(msg, bit_error_table, candidate_addrs.iter())
 }
 
 /// Process the stream result and do any decoding that is needed.
 fn process_result(
     result: stream::ProcessStreamResult,
-    bit_error_table: &HashMap<u32, u16>,
-    seen: &Arc<Mutex<HashMap<u32, Instant>>>
+    bit_error_table: &HashMap<u32, u32>,
+    seen: &Arc<Mutex<HashMap<u32, Instant>>>,
+    aggressive_crc_fix: bool
 ) -> Result<Message, MessageErrorReason> {
     let mut msg = result.msg;
 
@@ -219,27 +439,44 @@ fn process_result(
 
     if !crc_ok && (msgtype == 11 || msgtype == 17 || msgtype == 18) {
         nfixed = crc::fix_bit_errors(&mut msg, bit_error_table);
-        
+
         if nfixed == 0 {
             return Err(MessageErrorReason::BitErrors);
         }
 
+        if nfixed >= 2 && !aggressive_crc_fix {
+            return Err(MessageErrorReason::BitErrors);
+        }
+
         crc_syndrome = crc::modes_checksum(&msg);
         crc_ok = crc_syndrome == 0;
     }
 
+    // DF11/17/18 carry a genuine AA (address/announced) field in these three
+    // bytes. AP-format frames (DF0/4/5/16/20/21) don't - these same bytes are
+    // altitude/data bits, and the real ICAO address only comes out of
+    // `brute_force_ap`'s CRC-residual recovery below.
     let aa1 = msg[1];
     let aa2 = msg[2];
     let aa3 = msg[3];
-    let addr = ((aa1 as u32) << 16) | ((aa2 as u32) << 8) | aa3 as u32;    
+    let aa_addr = ((aa1 as u32) << 16) | ((aa2 as u32) << 8) | aa3 as u32;
+
+    let addr: u32;
 
     if msgtype != 11 && msgtype != 17 && msgtype != 18 {
-        if brute_force_ap(&msg, seen) {
-            crc_ok = true;
-        } else {
-            crc_ok = false;
+        match brute_force_ap(&mut msg, bit_error_table, seen) {
+            Some(recovered_addr) => {
+                crc_ok = true;
+                addr = recovered_addr;
+            },
+            None => {
+                crc_ok = false;
+                addr = aa_addr;
+            },
         }
     } else {
+        addr = aa_addr;
+
         if crc_ok && nfixed == 0 {
             seen.lock().unwrap().insert(addr, Instant::now());
         }
@@ -291,6 +528,7 @@ fn process_result(
         samples: result.samples,
         amplitudes: result.amplitudes,
         crc_ok: crc_ok,
+        nfixed: nfixed,
         pipe_ndx: result.pipe_ndx,
     };
 
@@ -422,16 +660,136 @@ fn process_result(
                     Ok(Message {
                         common: common,
                         specific: MessageSpecific::Other,
-                    })                    
+                    })
                 }
+            } else if metype == 28 && mesub == 1 {
+                Ok(Message {
+                    common: common,
+                    specific: MessageSpecific::AircraftStatus {
+                        hdr: hdr,
+                        emergency_state: bits::take_bits(&msg, 40, 3) as u8,
+                        squawk: bits::take_bits(&msg, 43, 13) as u32,
+                    },
+                })
+            } else if metype == 29 && (mesub == 1 || mesub == 2) {
+                Ok(Message {
+                    common: common,
+                    specific: MessageSpecific::TargetStateAndStatus {
+                        hdr: hdr,
+                        selected_altitude: bits::take_bits(&msg, 40, 11) as f32 * 32.0,
+                        barometric_setting: 800.0 + (bits::take_bits(&msg, 52, 9) as f32 - 1.0) * 0.1,
+                        selected_heading: bits::take_bits(&msg, 62, 9) as f32 * (180.0 / 256.0),
+                        autopilot_engaged: bits::take_bits(&msg, 75, 1) == 1,
+                        vnav_engaged: bits::take_bits(&msg, 76, 1) == 1,
+                        altitude_hold_engaged: bits::take_bits(&msg, 77, 1) == 1,
+                        lnav_engaged: bits::take_bits(&msg, 81, 1) == 1,
+                    },
+                })
+            } else if metype == 31 && (mesub == 0 || mesub == 1) {
+                Ok(Message {
+                    common: common,
+                    specific: MessageSpecific::OperationalStatus {
+                        hdr: hdr,
+                        version: bits::take_bits(&msg, 72, 3) as u8,
+                        nic_supplement: bits::take_bits(&msg, 75, 1) as u8,
+                        nac_p: bits::take_bits(&msg, 76, 4) as u8,
+                        sil: bits::take_bits(&msg, 82, 2) as u8,
+                    },
+                })
             } else {
                 Ok(Message {
                     common: common,
                     specific: MessageSpecific::Other,
-                })                
+                })
             }
 
         },
+        0 => Ok(Message {
+            common: common,
+            specific: MessageSpecific::ShortAirToAirSurveillance {
+                hdr: DfHeader1 {
+                    capability: ca,
+                    addr: addr,
+                    metype: metype,
+                    mesub: mesub,
+                    fs: fs,
+                    identity: identity,
+                },
+                vertical_status: bits::take_bits(&msg, 5, 1) as u8,
+                altitude: decode_ac13_field(&msg),
+            },
+        }),
+        4 => Ok(Message {
+            common: common,
+            specific: MessageSpecific::SurveillanceAltitudeReply {
+                hdr: DfHeader1 {
+                    capability: ca,
+                    addr: addr,
+                    metype: metype,
+                    mesub: mesub,
+                    fs: fs,
+                    identity: identity,
+                },
+                altitude: decode_ac13_field(&msg),
+            },
+        }),
+        5 => Ok(Message {
+            common: common,
+            specific: MessageSpecific::SurveillanceIdentityReply {
+                hdr: DfHeader1 {
+                    capability: ca,
+                    addr: addr,
+                    metype: metype,
+                    mesub: mesub,
+                    fs: fs,
+                    identity: identity,
+                },
+            },
+        }),
+        16 => Ok(Message {
+            common: common,
+            specific: MessageSpecific::LongAirToAirSurveillance {
+                hdr: DfHeader1 {
+                    capability: ca,
+                    addr: addr,
+                    metype: metype,
+                    mesub: mesub,
+                    fs: fs,
+                    identity: identity,
+                },
+                altitude: decode_ac13_field(&msg),
+                mv: msg[4..11].to_vec(),
+            },
+        }),
+        20 => Ok(Message {
+            common: common,
+            specific: MessageSpecific::CommBAltitudeReply {
+                hdr: DfHeader1 {
+                    capability: ca,
+                    addr: addr,
+                    metype: metype,
+                    mesub: mesub,
+                    fs: fs,
+                    identity: identity,
+                },
+                altitude: decode_ac13_field(&msg),
+                mb: msg[4..11].to_vec(),
+            },
+        }),
+        21 => Ok(Message {
+            common: common,
+            specific: MessageSpecific::CommBIdentityReply {
+                hdr: DfHeader1 {
+                    capability: ca,
+                    addr: addr,
+                    metype: metype,
+                    mesub: mesub,
+                    fs: fs,
+                    identity: identity,
+                },
+                mb: msg[4..11].to_vec(),
+            },
+        }),
         _ => Ok(Message {
             common: common,
             specific: MessageSpecific::Other,
@@ -439,27 +797,6 @@ fn process_result(
     }
 }
 
-/// Serialize the common elements of a message to a file.
-fn write_message_to_file(file: &mut File, m: &Message) {
-    file.write_all(bytes_of(&(m.common.msg.len() as u16))).unwrap();
-    file.write_all(&m.common.msg).unwrap();
-    file.write_all(bytes_of(&(m.common.samples.len() as u16))).unwrap();
-    for x in 0..m.common.samples.len() {
-        file.write_all(bytes_of(&m.common.samples[x])).unwrap();
-    }
-    file.write_all(bytes_of(&m.common.ndx)).unwrap();
-    file.write_all(bytes_of(&m.common.snr)).unwrap();
-    let thetas = &m.common.thetas;
-    file.write_all(bytes_of(&(thetas.len() as u8))).unwrap();
-    for theta in thetas {
-        file.write_all(bytes_of(theta)).unwrap();
-    }
-    let amplitudes = &m.common.amplitudes;
-    file.write_all(bytes_of(&(m.common.amplitudes.len() as u8))).unwrap();
-    for amp in amplitudes {
-        file.write_all(bytes_of(amp)).unwrap();
-    }
-}
 
 /// Anything with a transponder such as an aircraft.
 struct Entity {
@@ -474,12 +811,23 @@ struct Entity {
     /// The `odd_cpr` and `even_cpr` are used to compute a
     /// latitude and longitude.
     even_cpr: Option<(u32, u32, u64)>,
+    /// The odd raw lat and lon from a `SurfacePositionMessage`, kept separate from
+    /// `odd_cpr` since surface CPR uses a different (90 degree) grid and a pairing
+    /// from one format isn't valid input to the other's decoder.
+    surface_odd_cpr: Option<(u32, u32, u64)>,
+    /// The even raw lat and lon from a `SurfacePositionMessage`. See `surface_odd_cpr`.
+    surface_even_cpr: Option<(u32, u32, u64)>,
     /// The last known computed latitude from the CPR format.
     lat: Option<f32>,
     /// The last known computed longitude from the CPR format.
     lon: Option<f32>,
     /// The last known altitude.
     alt: Option<f32>,
+    /// The last known ground speed, in knots, from an `AirborneVelocityMessage`.
+    velocity: Option<f32>,
+    /// The last known heading, in degrees, from an `AirborneVelocityMessage` or
+    /// `AirborneVelocityMessageShort`.
+    heading: Option<f32>,
     /// The last known flight identifier transmitted.
     flight: Option<Vec<char>>,
     /// The last known aircraft type.
@@ -497,11 +845,15 @@ struct Entity {
     snrs: VecDeque<f32>,
     /// A list used to compute a rolling weighted average over the weight amplitudes.
     amps: VecDeque<Vec<f32>>,
-    /// The number of messages that matched the set steering vector. 
+    /// The number of messages that matched the set steering vector.
     ///
     /// This shows how effective the steering vector calculated is at
     /// capturing messages.
     inbeam: u64,
+    /// A bounded history of recent valid `(lat, lon, alt, sample_index)` position
+    /// fixes, used by `push_position_fix`/`smoothed_position` to reject outlier CPR
+    /// decodes and smooth the track.
+    position_history: VecDeque<(f32, f32, Option<f32>, u64)>,
 }
 
 impl Entity {
@@ -563,6 +915,24 @@ impl Entity {
         (sum, amp_sum)
     }
 
+    /// Resolves the steering vector to hand to `PipeManagement::set_addr_to_theta` for a
+    /// newly decoded message from this entity.
+    ///
+    /// Prefers `estimate_steering_vector`'s closed-form cross-correlation against the raw
+    /// samples of this message, since it's both cheaper and more precise than the random
+    /// search; falls back to the `push_theta_cap_avg` rolling average (the random search's
+    /// own result) when the sample window doesn't decompose cleanly into complex frames.
+    fn resolve_steering_vector(&mut self, common: &MessageCommon, weighted_avg_depth: usize, snr_scaler: f32) -> (Vec<f32>, Vec<f32>) {
+        let streams = common.amplitudes.len();
+
+        match estimate_steering_vector(&common.samples, streams) {
+            Some(result) => result,
+            None => self.push_theta_cap_avg(
+                common.snr, common.thetas.clone(), common.amplitudes.clone(), weighted_avg_depth, snr_scaler
+            ),
+        }
+    }
+
     /// Check if the pipe_ndx, likely from a message, is currently the target for the address.
     ///
     /// When we trying to track a transponder we try to compute a steering vector. If there are
@@ -582,6 +952,95 @@ impl Entity {
             None => (),
         }
     }
+
+    /// Offers a newly decoded `(lat, lon, alt)` fix at `sample_index` to the position
+    /// history, updating `lat`/`lon` and pushing it onto `position_history`.
+    ///
+    /// Clears the history first if the gap since its last entry exceeds
+    /// `history_timeout_secs`, since a stale history shouldn't be used to judge a fix
+    /// after a long quiet period. Otherwise, rejects the fix as an outlier (leaving
+    /// `lat`/`lon`/the history untouched) if the ground speed it implies relative to
+    /// the most recent history entry exceeds `max_speed_knots` -- this is what drops
+    /// the occasional glitch fix CPR decoding produces under multipath.
+    fn push_position_fix(
+        &mut self,
+        lat: f32,
+        lon: f32,
+        alt: Option<f32>,
+        sample_index: u64,
+        history_depth: usize,
+        max_speed_knots: f32,
+        history_timeout_secs: u64
+    ) {
+        if let Some(&(_, _, _, last_sample_index)) = self.position_history.back() {
+            if sample_index.saturating_sub(last_sample_index) / 2_000_000u64 > history_timeout_secs {
+                self.position_history.clear();
+            }
+        }
+
+        if let Some(&(last_lat, last_lon, _, last_sample_index)) = self.position_history.back() {
+            let dt_secs = sample_index.saturating_sub(last_sample_index) as f32 / 2_000_000.0;
+            if dt_secs > 0.0 {
+                let distance_nm = haversine_nm(last_lat, last_lon, lat, lon);
+                let speed_knots = distance_nm / (dt_secs / 3600.0);
+                if speed_knots > max_speed_knots {
+                    return;
+                }
+            }
+        }
+
+        self.lat = Some(lat);
+        self.lon = Some(lon);
+
+        self.position_history.push_back((lat, lon, alt, sample_index));
+        while self.position_history.len() > history_depth {
+            self.position_history.pop_front();
+        }
+    }
+
+    /// Returns the smoothed position: a plain average of `lat`/`lon` (and `alt`,
+    /// where present) over `position_history`. This is a stabler track than the raw
+    /// latest `lat`/`lon`, since it's less sensitive to any one glitch fix that made
+    /// it past `push_position_fix`'s outlier check.
+    fn smoothed_position(&self) -> Option<(f32, f32, Option<f32>)> {
+        if self.position_history.is_empty() {
+            return None;
+        }
+
+        let n = self.position_history.len() as f32;
+        let mut lat_sum = 0.0f32;
+        let mut lon_sum = 0.0f32;
+        let mut alt_sum = 0.0f32;
+        let mut alt_count = 0;
+
+        for &(lat, lon, alt, _) in &self.position_history {
+            lat_sum += lat;
+            lon_sum += lon;
+            if let Some(alt) = alt {
+                alt_sum += alt;
+                alt_count += 1;
+            }
+        }
+
+        let alt = if alt_count > 0 { Some(alt_sum / alt_count as f32) } else { None };
+
+        Some((lat_sum / n, lon_sum / n, alt))
+    }
+}
+
+/// Great-circle distance between two lat/lon points, in nautical miles.
+fn haversine_nm(lat1: f32, lon1: f32, lat2: f32, lon2: f32) -> f32 {
+    const EARTH_RADIUS_NM: f32 = 3440.065;
+
+    let lat1r = lat1.to_radians();
+    let lat2r = lat2.to_radians();
+    let dlat = (lat2 - lat1).to_radians();
+    let dlon = (lon2 - lon1).to_radians();
+
+    let a = (dlat / 2.0).sin().powi(2) + lat1r.cos() * lat2r.cos() * (dlon / 2.0).sin().powi(2);
+    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+
+    EARTH_RADIUS_NM * c
 }
 
 /// Initialize a new entity/aircraft is none is found.
@@ -593,9 +1052,13 @@ fn init_entity_if_not(addr: u32, entities: &mut HashMap<u32, Entity>) {
                 addr: addr,
                 odd_cpr: None,
                 even_cpr: None,
+                surface_odd_cpr: None,
+                surface_even_cpr: None,
                 lat: None,
                 lon: None,
                 alt: None,
+                velocity: None,
+                heading: None,
                 flight: None,
                 last_update: 0u64,
                 aircraft_type: None,
@@ -604,6 +1067,7 @@ fn init_entity_if_not(addr: u32, entities: &mut HashMap<u32, Entity>) {
                 amps: VecDeque::new(),
                 message_count: 0,
                 inbeam: 0,
+                position_history: VecDeque::new(),
             });
         },
     }
@@ -620,7 +1084,12 @@ fn process_messages(
     buffer_start_sample_index: u64,
     pipe_mgmt: &mut PipeManagement,
     snr_scaler: f32,
-    weighted_avg_depth: usize
+    weighted_avg_depth: usize,
+    track_history_depth: usize,
+    track_max_speed_knots: f32,
+    track_history_timeout_secs: u64,
+    ref_lat: Option<f32>,
+    ref_lon: Option<f32>
 ) {
     for (buffer_sample_index, m) in messages {
         let sample_index = buffer_sample_index as u64 + buffer_start_sample_index;
@@ -628,17 +1097,17 @@ fn process_messages(
         match m.specific {
             MessageSpecific::AirborneVelocityMessageShort {
                 hdr,
-                heading: _
+                heading
             } => {
                 init_entity_if_not(hdr.addr, entities);
                 let ent = entities.get_mut(&hdr.addr).unwrap();
+                ent.heading = Some(heading);
                 ent.message_count += 1;
 
                 ent.check_if_in_beam(pipe_mgmt, m.common.pipe_ndx);
+                pipe_mgmt.touch_addr(hdr.addr, Instant::now());
 
-                let (thetas, amps) = ent.push_theta_cap_avg(
-                    m.common.snr, m.common.thetas, m.common.amplitudes, weighted_avg_depth, snr_scaler
-                );
+                let (thetas, amps) = ent.resolve_steering_vector(&m.common, weighted_avg_depth, snr_scaler);
                 // Update get average set a pipe or existing pipe.
                 pipe_mgmt.set_addr_to_theta(
                     hdr.addr,
@@ -647,7 +1116,7 @@ fn process_messages(
                 );
             },
             MessageSpecific::AirborneVelocityMessage {
-                hdr, 
+                hdr,
                 ew_dir: _,
                 ew_velocity: _,
                 ns_dir: _,
@@ -655,19 +1124,20 @@ fn process_messages(
                 vert_rate_source: _,
                 vert_rate_sign: _,
                 vert_rate: _,
-                velocity: _,
-                heading: _
+                velocity,
+                heading
             } => {
                 init_entity_if_not(hdr.addr, entities);
                 let ent = entities.get_mut(&hdr.addr).unwrap();
+                ent.velocity = Some(velocity);
+                ent.heading = Some(heading);
                 ent.message_count += 1;
 
                 ent.check_if_in_beam(pipe_mgmt, m.common.pipe_ndx);
-                
+                pipe_mgmt.touch_addr(hdr.addr, Instant::now());
+
                 // Update get average set a pipe or existing pipe.
-                let (thetas, amps) = ent.push_theta_cap_avg(
-                    m.common.snr, m.common.thetas, m.common.amplitudes, weighted_avg_depth, snr_scaler
-                );
+                let (thetas, amps) = ent.resolve_steering_vector(&m.common, weighted_avg_depth, snr_scaler);
                 pipe_mgmt.set_addr_to_theta(
                     hdr.addr,
                     thetas,
@@ -687,11 +1157,10 @@ fn process_messages(
                 ent.message_count += 1;
 
                 ent.check_if_in_beam(pipe_mgmt, m.common.pipe_ndx);
+                pipe_mgmt.touch_addr(hdr.addr, Instant::now());
 
                 // Update get average set a pipe or existing pipe.
-                let (thetas, amps) = ent.push_theta_cap_avg(
-                    m.common.snr, m.common.thetas, m.common.amplitudes, weighted_avg_depth, snr_scaler
-                );
+                let (thetas, amps) = ent.resolve_steering_vector(&m.common, weighted_avg_depth, snr_scaler);
                 pipe_mgmt.set_addr_to_theta(
                     hdr.addr,
                     thetas,
@@ -713,11 +1182,10 @@ fn process_messages(
                 ent.message_count += 1;
 
                 ent.check_if_in_beam(pipe_mgmt, m.common.pipe_ndx);
+                pipe_mgmt.touch_addr(hdr.addr, Instant::now());
 
                 // Update get average set a pipe or existing pipe.
-                let (thetas, amps) = ent.push_theta_cap_avg(
-                    m.common.snr, m.common.thetas, m.common.amplitudes, weighted_avg_depth, snr_scaler
-                );
+                let (thetas, amps) = ent.resolve_steering_vector(&m.common, weighted_avg_depth, snr_scaler);
                 pipe_mgmt.set_addr_to_theta(
                     hdr.addr,
                     thetas,
@@ -730,6 +1198,23 @@ fn process_messages(
                     ent.even_cpr = Some((raw_lat, raw_lon, sample_index));
                 }
 
+                // Resolve this single frame against whichever reference point is
+                // available - the aircraft's own last known position if we have
+                // one, otherwise the receiver's `--ref-lat`/`--ref-lon` - so a fix
+                // doesn't have to wait for a matching even/odd pair. This runs on
+                // every frame (not just the first) since it's cheap and only ever
+                // adds a candidate fix for `push_position_fix`'s outlier check to
+                // accept or reject.
+                let relative_ref = ent.lat.zip(ent.lon).or_else(|| ref_lat.zip(ref_lon));
+                if let Some((rlat, rlon)) = relative_ref {
+                    if let Some((lat, lon)) = cpr::decode_cpr_relative((raw_lat, raw_lon), f_flag, rlat, rlon) {
+                        ent.push_position_fix(
+                            lat, lon, ent.alt, sample_index,
+                            track_history_depth, track_max_speed_knots, track_history_timeout_secs
+                        );
+                    }
+                }
+
                 match ent.even_cpr {
                     Some(a) => match ent.odd_cpr {
                         Some(b) => {
@@ -744,8 +1229,10 @@ fn process_messages(
                             if delta / 2000000u64 <= 10 {
                                 match decode_cpr(a, b) {
                                     Some((lat, lon)) => {
-                                        ent.lat = Some(lat);
-                                        ent.lon = Some(lon);
+                                        ent.push_position_fix(
+                                            lat, lon, ent.alt, sample_index,
+                                            track_history_depth, track_max_speed_knots, track_history_timeout_secs
+                                        );
                                     },
                                     None => (),
                                 }
@@ -756,6 +1243,78 @@ fn process_messages(
                     None => (),
                 }
             },
+            MessageSpecific::SurfacePositionMessage {
+                hdr,
+                movement,
+                ground_track,
+                f_flag,
+                t_flag: _,
+                raw_lat,
+                raw_lon,
+            } => {
+                init_entity_if_not(hdr.addr, entities);
+                let ent = entities.get_mut(&hdr.addr).unwrap();
+                ent.last_update = sample_index;
+                ent.message_count += 1;
+
+                if let Some(knots) = movement_to_knots(movement) {
+                    ent.velocity = Some(knots);
+                }
+                ent.heading = Some(ground_track_to_degrees(ground_track));
+
+                ent.check_if_in_beam(pipe_mgmt, m.common.pipe_ndx);
+                pipe_mgmt.touch_addr(hdr.addr, Instant::now());
+
+                // Update get average set a pipe or existing pipe.
+                let (thetas, amps) = ent.resolve_steering_vector(&m.common, weighted_avg_depth, snr_scaler);
+                pipe_mgmt.set_addr_to_theta(
+                    hdr.addr,
+                    thetas,
+                    Some(amps)
+                );
+
+                if f_flag {
+                    ent.surface_odd_cpr = Some((raw_lat, raw_lon, sample_index));
+                } else {
+                    ent.surface_even_cpr = Some((raw_lat, raw_lon, sample_index));
+                }
+
+                // Unlike airborne frames, the surface CPR grid only spans a 90
+                // degree quadrant, so there's no unambiguous single-frame
+                // decode without a reference point - `decode_cpr_surface` needs
+                // one to pick the right quadrant, and only the receiver's
+                // configured `--ref-lat`/`--ref-lon` can supply it here (no
+                // matched pair means no prior aircraft position yet).
+                if let (Some(rlat), Some(rlon)) = (ref_lat, ref_lon) {
+                    match ent.surface_even_cpr {
+                        Some(a) => match ent.surface_odd_cpr {
+                            Some(b) => {
+                                let delta = if a.2 > b.2 {
+                                    a.2 - b.2
+                                } else {
+                                    b.2 - a.2
+                                };
+
+                                // Divide delta by the sample rate for the number of seconds
+                                // between each pair of raw coordinates.
+                                if delta / 2000000u64 <= 10 {
+                                    match cpr::decode_cpr_surface(a, b, rlat, rlon) {
+                                        Some((lat, lon)) => {
+                                            ent.push_position_fix(
+                                                lat, lon, ent.alt, sample_index,
+                                                track_history_depth, track_max_speed_knots, track_history_timeout_secs
+                                            );
+                                        },
+                                        None => (),
+                                    }
+                                }
+                            },
+                            None => (),
+                        },
+                        None => (),
+                    }
+                }
+            },
             _ => (),
         }
     }
@@ -773,10 +1332,39 @@ struct Args {
     #[arg(short, long)]
     cycle_count: u32,
 
-    /// A file prefix to write messages.
+    /// A file to write captured messages to, in the versioned `capture` format.
     #[arg(short, long)]
     file_output: Option<String>,
 
+    /// The sample rate the capture file's header records. Purely informational for
+    /// replay; it isn't used to reinterpret the recorded samples.
+    #[arg(long)]
+    #[clap(default_value_t = 2_000_000)]
+    capture_sample_rate: u32,
+
+    /// Replay a capture file written with `--file-output` instead of connecting to
+    /// the live sample stream. Feeds each recorded (already decoded) message to the
+    /// net outputs and `process_messages`, without running `process_result` again, so
+    /// tracking and output changes can be regression-tested against a fixed recording.
+    #[arg(long)]
+    replay: Option<String>,
+
+    /// Records the raw I/Q buffer thread 0 hands to `stream::process_buffer`, plus
+    /// its stream count and pipe theta/amplitude configuration, to a WAV file (with
+    /// a JSON sidecar) at this path. Unlike `--file-output`, this captures the
+    /// beamformer's input rather than its decoded output, so `--replay-iq` can
+    /// regression-test the beamforming and preamble-detection code itself.
+    #[arg(long)]
+    iq_capture_output: Option<String>,
+
+    /// Replay an I/Q capture written with `--iq-capture-output` through
+    /// `stream::process_buffer` instead of connecting to the live sample stream. The
+    /// whole recording is decoded as one buffer with MVDR and the auto-notch off and
+    /// a fresh `seen` table, and pipes without a recorded theta draw new random
+    /// thetas, so the result is not guaranteed to equal the live run's messages.
+    #[arg(long)]
+    replay_iq: Option<String>,
+
     /// TCP address to output raw messages to.
     #[arg(short, long)]
     net_raw_out: Option<String>,
@@ -799,6 +1387,164 @@ struct Args {
     #[arg(short, long)]
     #[clap(default_value_t = true)]
     randomize_amplitudes: bool,
+
+    /// Enables MVDR adaptive beamforming in place of the fixed-theta steered sum.
+    /// Only the 2-antenna case is supported; other stream counts ignore this flag.
+    #[arg(long)]
+    #[clap(default_value_t = false)]
+    mvdr: bool,
+
+    /// Number of samples the MVDR spatial covariance estimate is averaged over.
+    #[arg(long)]
+    #[clap(default_value_t = 4096)]
+    mvdr_window: usize,
+
+    /// Diagonal loading applied to the MVDR covariance matrix, as a fraction of its trace.
+    #[arg(long)]
+    #[clap(default_value_t = 0.01)]
+    mvdr_loading: f32,
+
+    /// How many buffers a worker thread may lag behind before `send_buffer_to_all` starts
+    /// dropping buffers for it instead of blocking the whole cycle.
+    #[arg(long)]
+    #[clap(default_value_t = 2)]
+    pipe_queue_depth: usize,
+
+    /// Accept DF11/17/18 frames that only pass their CRC after flipping two bits, not
+    /// just one. Off by default since two-bit correction meaningfully raises the odds
+    /// of accepting a frame that merely happened to checksum by chance.
+    #[arg(long)]
+    #[clap(default_value_t = false)]
+    aggressive_crc_fix: bool,
+
+    /// How many simultaneously flipped bits `crc::fix_bit_errors` will try to correct.
+    /// 2 (the default) covers single- and double-bit errors; 3 also builds and checks
+    /// against the much larger triple-bit syndrome table (see
+    /// `crc::modes_init_error_info_depth`'s doc comment for the size tradeoff) for very
+    /// noisy RF environments. Values above 3 use the same triple-bit table, since no
+    /// deeper table is generated; values below 2 use the default depth-2 table.
+    #[arg(long)]
+    #[clap(default_value_t = 2)]
+    crc_fix_depth: usize,
+
+    /// Enables the adaptive FFT auto-notch filter, which tracks and subtracts the
+    /// strongest narrowband bins from the combined complex stream before magnitude
+    /// is taken, so a strong CW/narrowband interferer doesn't raise the noise floor
+    /// and fail the preamble test. Only applies to the generic (non-x2/x4) combine
+    /// path in `process_buffer_single`.
+    #[arg(long)]
+    #[clap(default_value_t = false)]
+    notch: bool,
+
+    /// How many of the strongest FFT bins the auto-notch filter tracks.
+    #[arg(long)]
+    #[clap(default_value_t = 4)]
+    notch_slots: usize,
+
+    /// Block size (in complex samples) the auto-notch filter's FFT runs over; must
+    /// be a power of two.
+    #[arg(long)]
+    #[clap(default_value_t = 4096)]
+    notch_block_size: usize,
+
+    /// IIR update rate for the auto-notch filter's per-bin amplitude estimate.
+    #[arg(long)]
+    #[clap(default_value_t = 0.002)]
+    notch_mu: f32,
+
+    /// How many recent valid position fixes `Entity::position_history` retains for
+    /// outlier rejection and track smoothing.
+    #[arg(long)]
+    #[clap(default_value_t = 8)]
+    track_history_depth: usize,
+
+    /// Maximum plausible ground speed, in knots, implied between consecutive
+    /// position fixes. A new fix implying a higher speed is rejected as an outlier.
+    #[arg(long)]
+    #[clap(default_value_t = 1200.0)]
+    track_max_speed_knots: f32,
+
+    /// Seconds a gap between position fixes may span before the position history is
+    /// cleared instead of being used to judge the next fix.
+    #[arg(long)]
+    #[clap(default_value_t = 300)]
+    track_history_timeout_secs: u64,
+
+    /// Receiver reference latitude, in degrees. Combined with `--ref-lon`, this lets
+    /// `cpr::decode_cpr_relative` produce an immediate airborne position fix from a
+    /// single extended-squitter frame (instead of waiting for a matched even/odd
+    /// pair) and lets `cpr::decode_cpr_surface` resolve which of the four 90 degree
+    /// quadrants a surface position belongs to. Position tracking for a given
+    /// aircraft still works without this once its first paired fix is known (that
+    /// known position becomes the reference for relative decodes from then on); for
+    /// airborne positions it only affects how soon the very first fix shows up.
+    /// Surface positions are only decoded when both `--ref-lat` and `--ref-lon` are set.
+    #[arg(long)]
+    ref_lat: Option<f32>,
+
+    /// Receiver reference longitude, in degrees. See `--ref-lat`.
+    #[arg(long)]
+    ref_lon: Option<f32>,
+
+    /// Seconds an assigned pipe may go without hearing from its aircraft before it is
+    /// released back to random search.
+    #[arg(long)]
+    #[clap(default_value_t = 60)]
+    pipe_idle_timeout_secs: u64,
+
+    /// Bind address (e.g. 0.0.0.0:30005) to serve decoded messages as Beast binary frames.
+    #[arg(long)]
+    beast_out: Option<String>,
+
+    /// Bind address to serve decoded messages as AVR raw hex (`*<hex>;`).
+    #[arg(long)]
+    avr_out: Option<String>,
+
+    /// Bind address to serve decoded messages as SBS-1 "BaseStation" CSV.
+    #[arg(long)]
+    sbs_out: Option<String>,
+
+    /// Bind address to serve decoded messages as tab-separated key/value records.
+    #[arg(long)]
+    tsv_out: Option<String>,
+
+    /// How many records a net output client may lag behind before it is dropped
+    /// instead of stalling the decode thread.
+    #[arg(long)]
+    #[clap(default_value_t = 64)]
+    net_out_client_queue_depth: usize,
+
+    /// Address of an upstream Mode S receiver speaking Beast binary to fuse with our
+    /// own beamformed detections. May be given multiple times.
+    #[arg(long)]
+    upstream_beast: Vec<String>,
+
+    /// Address of an upstream Mode S receiver speaking AVR raw to fuse with our own
+    /// beamformed detections. May be given multiple times.
+    #[arg(long)]
+    upstream_avr: Vec<String>,
+}
+
+/// Connects to `addr`, retrying every 5 seconds on failure instead of panicking, the
+/// same backoff `netin::connect` uses for upstream feeds. Bails out early with `None`
+/// if `shutdown` is set while waiting between attempts, so Ctrl-C during a reconnect
+/// doesn't have to wait out the full backoff.
+fn connect_with_retry(addr: &str, shutdown: &AtomicBool) -> Option<TcpStream> {
+    loop {
+        match TcpStream::connect(addr) {
+            Ok(stream) => return Some(stream),
+            Err(e) => println!("failed to connect to {}: {}, retrying in 5s", addr, e),
+        }
+
+        // Sleep the 5s backoff in short steps so a shutdown request is seen promptly.
+        for _ in 0..50 {
+            if shutdown.load(Ordering::Relaxed) {
+                return None;
+            }
+
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
 }
 
 fn main() {
@@ -813,40 +1559,157 @@ fn main() {
 
     let server_addr = "127.0.0.1:7878";
 
-    let mut pipe_mgmt = PipeManagement::new(thread_count as usize, cycle_count as usize);
+    // Set once by the Ctrl-C handler; worker threads and the read loop poll it to
+    // unwind cleanly instead of the process being killed mid-buffer.
+    let shutdown: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+    {
+        let shutdown = shutdown.clone();
+        ctrlc::set_handler(move || {
+            println!("shutdown requested, finishing current cycle...");
+            shutdown.store(true, Ordering::Relaxed);
+        }).expect("failed to install Ctrl-C handler");
+    }
 
-    let mut rxs: Vec<Receiver<Vec<Message>>> = Vec::new();
+    let mut pipe_mgmt = PipeManagement::new(thread_count as usize, cycle_count as usize);
+    pipe_mgmt.set_idle_timeout(Duration::from_secs(args.pipe_idle_timeout_secs));
+
+    // Every worker's results land on this one channel, tagged with the worker's
+    // index and the cycle its buffer was sent under, instead of the main loop
+    // owning one `Receiver` per worker. Polling N separate `Receiver`s each with
+    // their own `recv_timeout` means N simultaneously stalled workers cost up to N
+    // times that timeout per cycle; folding them into a single channel means the
+    // main loop waits out one shared deadline for the whole cycle no matter how many
+    // workers are behind. The cycle tag lets a result that arrives late - from a
+    // buffer `send_buffer_to_all` had to drop for a slow worker on some earlier
+    // cycle - be told apart from a genuine answer to the cycle currently collecting.
+    let (result_tx, result_rx): (Sender<(usize, u64, Vec<Message>)>, Receiver<(usize, u64, Vec<Message>)>) = channel();
     let seen: Arc<Mutex<HashMap<u32, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
 
+    // Worker threads hand decoded messages back through `result_tx` as a fresh
+    // `Vec<Message>` per cycle; at 2 MSPS that's one allocation per thread per
+    // buffer. `message_pool` lets the main loop recycle each `Vec<Message>` back to
+    // whichever thread needs one next instead of letting it drop and allocating again.
+    let message_pool: recycler::Recycler<Vec<Message>> = recycler::Recycler::new();
+
     for x in 0..thread_count as usize {
-        let (atx, brx) = channel();
-        let (btx, arx) = channel();
-        pipe_mgmt.push_tx(atx);
-        rxs.push(arx);
+        let (atx, brx) = std::sync::mpsc::sync_channel(args.pipe_queue_depth);
+        let btx = result_tx.clone();
+        let (ftx, frx) = std::sync::mpsc::sync_channel(cycle_count as usize * 4);
+        pipe_mgmt.push_tx(atx, frx);
 
         let seen_thread = seen.clone();
+        let message_pool_thread = message_pool.clone();
+        let shutdown_thread = shutdown.clone();
 
         let base_pipe_ndx: usize = x * cycle_count as usize;
 
         thread::spawn(move || {
             println!("spawned");
-            let bit_error_table = crc::modes_init_error_info();
+            let bit_error_table = crc::modes_init_error_info_for_depth(args.crc_fix_depth);
             let mut pipe_theta: Vec<Option<Vec<f32>>> = vec![None; cycle_count as usize];
             let mut pipe_amps: Vec<Option<Vec<f32>>> = vec![None; cycle_count as usize];
+            let mut pipe_covariance: Vec<mvdr::Covariance> = if args.mvdr {
+                (0..cycle_count as usize).map(|_| mvdr::Covariance::new(args.mvdr_window)).collect()
+            } else {
+                Vec::new()
+            };
+            let mut pipe_notch: Vec<Option<notch::AutoNotch>> = if args.notch {
+                (0..cycle_count as usize)
+                    .map(|_| Some(notch::AutoNotch::new(args.notch_slots, args.notch_block_size, args.notch_mu)))
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            // Only thread 0 records an I/Q capture, so a single WAV/sidecar pair
+            // covers one `base_pipe_ndx`/pipe-config fixture rather than one per
+            // thread. Created lazily on the first buffer since `streams` isn't
+            // known until then.
+            let mut iq_capture_writer: Option<iqcapture::IqCaptureWriter> = None;
 
             loop {
-                match brx.recv().unwrap() {
-                    ThreadTxMessage::Buffer(buffer, streams) => {
-                        btx.send(stream::process_buffer(
+                // A short timeout instead of a blocking `recv()` so this thread notices
+                // `shutdown` and exits instead of sitting on the channel forever when the
+                // read loop stops sending it buffers.
+                let msg = match brx.recv_timeout(Duration::from_millis(500)) {
+                    Ok(msg) => msg,
+                    Err(RecvTimeoutError::Timeout) => {
+                        if shutdown_thread.load(Ordering::Relaxed) {
+                            println!("worker {} shutting down", x);
+                            return;
+                        }
+                        continue;
+                    },
+                    Err(RecvTimeoutError::Disconnected) => {
+                        println!("worker {} disconnected from pipe management, shutting down", x);
+                        return;
+                    },
+                };
+
+                match msg {
+                    ThreadTxMessage::Buffer(buffer, streams, cycle) => {
+                        if x == 0 {
+                            if let Some(path) = &args.iq_capture_output {
+                                if iq_capture_writer.is_none() {
+                                    iq_capture_writer = Some(
+                                        iqcapture::IqCaptureWriter::create(
+                                            path, args.capture_sample_rate, streams, base_pipe_ndx, &pipe_theta, &pipe_amps
+                                        ).unwrap()
+                                    );
+                                }
+
+                                if let Some(writer) = &mut iq_capture_writer {
+                                    writer.write_buffer(&buffer).unwrap();
+                                }
+                            }
+                        }
+
+                        let mut messages = message_pool_thread.take(Vec::new);
+                        stream::process_buffer(
                             &buffer,
-                            &bit_error_table,
+                            bit_error_table,
                             &pipe_theta,
                             &pipe_amps,
                             streams,
                             &seen_thread,
                             base_pipe_ndx,
-                            args.randomize_amplitudes
-                        )).unwrap();
+                            args.randomize_amplitudes,
+                            &mut pipe_covariance,
+                            args.mvdr_loading,
+                            args.aggressive_crc_fix,
+                            &mut pipe_notch,
+                            &mut messages
+                        );
+
+                        // Tally per-pipe quality so PipeManagement can see which pipes
+                        // are producing good decodes and which have gone quiet.
+                        let mut best_snr = vec![0.0f32; cycle_count as usize];
+                        let mut decode_count = vec![0u32; cycle_count as usize];
+                        let mut best_theta: Vec<Vec<f32>> = vec![Vec::new(); cycle_count as usize];
+
+                        for message in &messages {
+                            let local_pipe_ndx = message.common.pipe_ndx - base_pipe_ndx;
+                            decode_count[local_pipe_ndx] += 1;
+                            if message.common.snr > best_snr[local_pipe_ndx] {
+                                best_snr[local_pipe_ndx] = message.common.snr;
+                                best_theta[local_pipe_ndx] = message.common.thetas.clone();
+                            }
+                        }
+
+                        for local_pipe_ndx in 0..cycle_count as usize {
+                            let report = PipeReport {
+                                snr: best_snr[local_pipe_ndx],
+                                decode_count: decode_count[local_pipe_ndx],
+                                theta: pipe_theta[local_pipe_ndx].clone()
+                                    .unwrap_or_else(|| std::mem::take(&mut best_theta[local_pipe_ndx])),
+                            };
+                            // Feedback is best-effort: if the manager hasn't drained the
+                            // last cycle's reports yet we just drop this one rather than
+                            // stalling the decode loop.
+                            let _ = ftx.try_send(ThreadRxMessage::Report(local_pipe_ndx, report));
+                        }
+
+                        btx.send((x, cycle, messages)).unwrap();
                     },
                     ThreadTxMessage::SetWeights(pipe_ndx, thetas, amps) => {
                         pipe_theta[pipe_ndx] = Some(thetas);
@@ -862,15 +1725,19 @@ fn main() {
         });
     }
 
-    let mut file = match args.file_output {
-        Some(v) => {
-            Some(File::create(v).unwrap())
-        },
-        None => None,
-    };
-
     let mut entities: HashMap<u32, Entity> = HashMap::new();
 
+    // Upstream feeds we fuse with our own beamformed detections, so the beamformer
+    // can be cross-checked against a conventional omnidirectional receiver.
+    let upstream_bit_error_table = crc::modes_init_error_info_for_depth(args.crc_fix_depth);
+    let mut upstream_rxs: Vec<Receiver<stream::ProcessStreamResult>> = Vec::new();
+    for addr in &args.upstream_beast {
+        upstream_rxs.push(netin::connect(addr, UpstreamFormat::Beast));
+    }
+    for addr in &args.upstream_avr {
+        upstream_rxs.push(netin::connect(addr, UpstreamFormat::Avr));
+    }
+
     let mut sample_index: u64 = 0;
 
     let mut buffer_time_elapsed_avg = 0.0f64;
@@ -883,41 +1750,197 @@ fn main() {
     let mut stat_start = Instant::now();
     let stat_gstart = Instant::now();
 
-    let mut net_raw_out_stream: Option<TcpStream> = match args.net_raw_out {
+    let net_output = NetOutput {
+        beast: args.beast_out.as_ref().map(|addr| {
+            println!("serving beast output on {}", addr);
+            let server = NetOutput::bind_or_panic(addr, args.net_out_client_queue_depth);
+            // Keep idle Beast connections alive with the standard null-packet
+            // heartbeat so routers between here and the consumer don't drop them.
+            server.spawn_heartbeat(netout::BEAST_HEARTBEAT.to_vec(), Duration::from_secs(1));
+            server
+        }),
+        avr: args.avr_out.as_ref().map(|addr| {
+            println!("serving avr output on {}", addr);
+            NetOutput::bind_or_panic(addr, args.net_out_client_queue_depth)
+        }),
+        sbs: args.sbs_out.as_ref().map(|addr| {
+            println!("serving sbs output on {}", addr);
+            NetOutput::bind_or_panic(addr, args.net_out_client_queue_depth)
+        }),
+        tsv: args.tsv_out.as_ref().map(|addr| {
+            println!("serving tsv output on {}", addr);
+            NetOutput::bind_or_panic(addr, args.net_out_client_queue_depth)
+        }),
+    };
+
+    let mut net_raw_out_stream: Option<TcpStream> = match &args.net_raw_out {
         None => None,
-        Some(addr) => match TcpStream::connect(addr.clone()) {
-            Ok(stream) => {
-                println!("connected to --net-raw-out {}", addr);
-                Some(stream)
-            },
-            Err(e) => {
-                println!("{}", e);
-                panic!("failed to connect to --net-raw-out")
-            },
-        },
+        Some(addr) => connect_with_retry(addr, &shutdown).map(|stream| {
+            println!("connected to --net-raw-out {}", addr);
+            stream
+        }),
     };
 
-    match TcpStream::connect(server_addr) {
-        Ok(mut stream) => {
+    if let Some(path) = &args.replay {
+        println!("replaying capture {}", path);
+        let mut reader = capture::CaptureReader::open(path).unwrap();
+        println!("capture sample_rate={} streams={}", reader.sample_rate, reader.streams);
+
+        let mut replay_items: Vec<(u64, Message)> = Vec::new();
+
+        // The capture already holds fully decoded messages, so replay just feeds
+        // them back through tracking/output rather than re-running process_result.
+        while let Some(message) = reader.read_record().unwrap() {
+            let entity = netout::addr_of(&message).and_then(|addr| entities.get(&addr));
+            net_output.emit(&message, entity);
+            replay_items.push((message.common.ndx, message));
+        }
+
+        println!("replayed {} messages", replay_items.len());
+
+        process_messages(
+            replay_items,
+            &mut entities,
+            0,
+            &mut pipe_mgmt,
+            args.snr_scaler,
+            args.weighted_avg_depth,
+            args.track_history_depth,
+            args.track_max_speed_knots,
+            args.track_history_timeout_secs,
+            args.ref_lat,
+            args.ref_lon
+        );
+
+        return;
+    }
+
+    if let Some(path) = &args.replay_iq {
+        println!("replaying iq capture {}", path);
+        let mut reader = iqcapture::IqCaptureReader::open(path).unwrap();
+        println!("iq capture streams={} base_pipe_ndx={}", reader.streams, reader.base_pipe_ndx);
+
+        let buffer = reader.read_buffer().unwrap();
+        let bit_error_table = crc::modes_init_error_info_for_depth(args.crc_fix_depth);
+        let mut pipe_covariance: Vec<mvdr::Covariance> = Vec::new();
+        let mut pipe_notch: Vec<Option<notch::AutoNotch>> = Vec::new();
+        let mut messages: Vec<Message> = Vec::new();
+
+        // Replays through the same `process_buffer` entry point the live worker
+        // threads use, with the recorded theta/amplitude config. The whole recording
+        // is one buffer here, MVDR and the notch are off, `seen` starts empty, and
+        // pipes without a recorded theta draw new random thetas, so the messages
+        // can differ from the live run's.
+        stream::process_buffer(
+            &buffer,
+            bit_error_table,
+            &reader.pipe_theta,
+            &reader.pipe_amps,
+            reader.streams,
+            &seen,
+            reader.base_pipe_ndx,
+            args.randomize_amplitudes,
+            &mut pipe_covariance,
+            args.mvdr_loading,
+            args.aggressive_crc_fix,
+            &mut pipe_notch,
+            &mut messages
+        );
+
+        println!("decoded {} messages from iq capture", messages.len());
+
+        let replay_items: Vec<(u64, Message)> = messages.into_iter()
+            .map(|message| (message.common.ndx, message))
+            .collect();
+
+        for (_, message) in &replay_items {
+            let entity = netout::addr_of(message).and_then(|addr| entities.get(&addr));
+            net_output.emit(message, entity);
+        }
+
+        process_messages(
+            replay_items,
+            &mut entities,
+            0,
+            &mut pipe_mgmt,
+            args.snr_scaler,
+            args.weighted_avg_depth,
+            args.track_history_depth,
+            args.track_max_speed_knots,
+            args.track_history_timeout_secs,
+            args.ref_lat,
+            args.ref_lon
+        );
+
+        return;
+    }
+
+    match connect_with_retry(server_addr, &shutdown) {
+        Some(mut stream) => {
             println!("connected");
+            // Without this the read loop below blocks forever on `stream.read` and
+            // never gets a chance to notice `shutdown`.
+            stream.set_read_timeout(Some(Duration::from_millis(500))).expect("failed to set read timeout");
             // We are expecting TWO interleaved streams from TWO antennas.
             let mut read: usize = 0;
             
             let mut short_buffer = vec![0; 1];
-            // Read the number of streams.
-            let streams = match stream.read(&mut short_buffer[0..1]) {
-                Ok(bytes_read) if bytes_read > 0 => {
-                    short_buffer[0] as usize
-                },
-                Ok(_) => {
-                    panic!("Sample stream TCP connection returned zero bytes.");
-                },
-                Err(e) => {
-                    panic!("Error: {}", e);
+            // Read the number of streams, retrying past the read timeout set above
+            // until either the byte arrives or shutdown is requested.
+            let streams = loop {
+                match stream.read(&mut short_buffer[0..1]) {
+                    Ok(bytes_read) if bytes_read > 0 => break short_buffer[0] as usize,
+                    Ok(_) => {
+                        panic!("Sample stream TCP connection returned zero bytes.");
+                    },
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock || e.kind() == std::io::ErrorKind::TimedOut => {
+                        if shutdown.load(Ordering::Relaxed) {
+                            println!("shutdown requested before the stream count arrived");
+                            return;
+                        }
+                    },
+                    Err(e) => {
+                        panic!("Error: {}", e);
+                    }
                 }
             };
 
-            let mut buffer: Vec<u8> = vec![0; MODES_LONG_MSG_SAMPLES * 1024 * (streams * 4)];
+            // Shares its reclaimed buffers with the hot read loop below, so the
+            // per-cycle `vec![0; buffer_len]` reallocation only happens until the
+            // pool warms up.
+            let buffer_pool: recycler::Recycler<Vec<u8>> = recycler::Recycler::new();
+
+            // A Mode S frame decoded near the very end of a buffer can have its
+            // preamble or payload cut off by the buffer boundary and never get
+            // tried again. Reserve room at the front of every buffer for the
+            // trailing `overlap_samples` of the previous one, carried forward so
+            // those straddling frames get a second, complete look.
+            let overlap_samples: usize = MODES_LONG_MSG_SAMPLES;
+            let overlap_bytes: usize = overlap_samples * streams * 4;
+
+            let mut buffer: Vec<u8> = buffer_pool.take(|| vec![0; overlap_bytes + MODES_LONG_MSG_SAMPLES * 1024 * (streams * 4)]);
+            let buffer_len = buffer.len();
+            // The overlap region is already populated (zeroed for the very first
+            // buffer, carried-over samples after that), so the first real read
+            // only needs to fill in the rest.
+            read = overlap_bytes;
+
+            // Absolute `ndx` of a message found in the overlap region can collide
+            // with one already emitted last cycle, since that region is a second
+            // look at data we've already scanned. Remember last cycle's absolute
+            // `ndx`s so those duplicates get dropped instead of double-reported.
+            let mut prev_ndxs: HashSet<u64> = HashSet::new();
+
+            // Identifies which buffer a worker's result answers, since
+            // `send_buffer_to_all`'s `try_send` can drop a buffer for a lagging
+            // worker - that worker keeps decoding whatever buffer it last got, and
+            // its eventual result needs to be told apart from a genuine answer to
+            // the cycle currently being collected below.
+            let mut buffer_cycle: u64 = 0;
+
+            let mut capture_writer = args.file_output.as_ref().map(|path| {
+                capture::CaptureWriter::create(path, args.capture_sample_rate, streams as u8).unwrap()
+            });
 
             match args.ula_spacing_wavelength {
                 None => (),
@@ -945,10 +1968,10 @@ fn main() {
             println!("working with {} streams", streams);
 
             let sps: f64 = 2e6f64;
-            let buffer_time: f64 = buffer.len() as f64 / (streams as f64 * 4.0f64) /  sps;
+            // Only `buffer_len - overlap_bytes` bytes are actually read from the
+            // network each cycle; the overlap prefix is carried over in memory.
+            let buffer_time: f64 = (buffer_len - overlap_bytes) as f64 / (streams as f64 * 4.0f64) /  sps;
             println!("reading stream");
-            // TODO: Take the tail end of the buffer and prefix it to the
-            // next buffer incase a message is across the two buffers.
             while match stream.read(&mut buffer[read..]) {
                 Ok(bytes_read) if bytes_read > 0 => {
                     read += bytes_read;
@@ -958,13 +1981,67 @@ fn main() {
                         let start = Instant::now();
 
                         let mut hm: HashMap<u64, Message> = HashMap::new();
-                        
-                        pipe_mgmt.send_buffer_to_all(&buffer, streams);
+
+                        // Hand the filled buffer to every thread as a shared `Arc` so the
+                        // broadcast only bumps a refcount per thread instead of cloning
+                        // the whole sample buffer T times, then swap in a buffer from the
+                        // pool (or allocate one if the pool is empty) for the next read,
+                        // seeded with this buffer's tail so the next cycle starts with
+                        // the carried-over overlap already in place.
+                        let mut next_buffer = buffer_pool.take(|| vec![0; buffer_len]);
+                        next_buffer[..overlap_bytes].copy_from_slice(&buffer[buffer_len - overlap_bytes..]);
+                        let shared_buffer = Arc::new(std::mem::replace(&mut buffer, next_buffer));
+
+                        buffer_cycle = buffer_cycle.wrapping_add(1);
+
+                        if let Err(stalled) = pipe_mgmt.send_buffer_to_all(&shared_buffer, streams, buffer_cycle) {
+                            for thread_ndx in stalled {
+                                println!("thread {} is behind, dropped this buffer for it", thread_ndx);
+                            }
+                        }
 
                         //println!("getting data from threads");
-                        for rx in &rxs {
-                            //println!("reading from one thread");
-                            for message in rx.recv().unwrap() {
+                        // All workers report into the one shared `result_rx`, so instead of
+                        // budgeting 2s per worker (up to thread_count * 2s if several stall
+                        // at once) this waits out a single 2s deadline for the whole cycle,
+                        // taking whichever workers answer within it.
+                        let mut responded: HashSet<usize> = HashSet::new();
+                        let deadline = Instant::now() + Duration::from_secs(2);
+                        while responded.len() < thread_count as usize {
+                            let remaining = deadline.saturating_duration_since(Instant::now());
+                            if remaining.is_zero() {
+                                break;
+                            }
+
+                            let (rx_ndx, result_cycle, mut messages) = match result_rx.recv_timeout(remaining) {
+                                Ok(result) => result,
+                                Err(RecvTimeoutError::Timeout) => break,
+                                Err(RecvTimeoutError::Disconnected) => {
+                                    println!("all worker threads are gone, skipping remaining results this cycle");
+                                    break;
+                                },
+                            };
+
+                            // A result tagged with an older cycle is a worker still
+                            // catching up on a buffer `send_buffer_to_all` dropped for
+                            // it earlier - not an answer to the cycle being collected
+                            // here. Processing it would globalize its messages'
+                            // `common.ndx` against the wrong `sample_index` below and
+                            // could poison `prev_ndxs`, so it's logged and skipped
+                            // instead of being folded into `hm`.
+                            if result_cycle != buffer_cycle {
+                                println!(
+                                    "thread {} answered for stale cycle {} (current cycle is {}), dropping its results",
+                                    rx_ndx, result_cycle, buffer_cycle
+                                );
+                                messages.clear();
+                                message_pool.give(messages);
+                                continue;
+                            }
+
+                            responded.insert(rx_ndx);
+
+                            for message in messages.drain(..) {
                                 // We are highly likely to get the same message from multiple
                                 // threads. We should take the highest SNR of any duplicates.
                                 match hm.get(&message.common.ndx) {
@@ -972,7 +2049,7 @@ fn main() {
                                         // Compare the SNR (signal to noise) ratio
                                         // and replace the existing if better.
                                         if other.common.snr < message.common.snr {
-                                            hm.insert(message.common.ndx, message);    
+                                            hm.insert(message.common.ndx, message);
                                         }
                                     },
                                     None => {
@@ -983,6 +2060,25 @@ fn main() {
                                     },
                                 }
                             }
+                            // Return the now-empty (but still allocated) Vec to the pool so
+                            // whichever thread processes the next buffer can reuse it.
+                            message_pool.give(messages);
+                        }
+
+                        if responded.len() < thread_count as usize {
+                            println!(
+                                "{} of {} threads didn't respond within 2s, skipping their results this cycle",
+                                thread_count as usize - responded.len(), thread_count
+                            );
+                        }
+
+                        // If every thread has finished with it, `shared_buffer`'s Arc is back
+                        // down to just this reference; reclaim it into the pool instead of
+                        // letting it drop, so the next cycle's buffer doesn't need a fresh
+                        // allocation. A thread that missed the deadline may still hold it,
+                        // in which case it is simply dropped.
+                        if let Ok(reclaimed) = Arc::try_unwrap(shared_buffer) {
+                            buffer_pool.give(reclaimed);
                         }
 
                         let mut items: Vec<(u64, Message)> = hm.into_iter().collect();
@@ -996,6 +2092,12 @@ fn main() {
                             message.common.ndx += sample_index;
                         }
 
+                        // The overlap region prepended onto this buffer is a second
+                        // look at samples already scanned in the previous cycle, so
+                        // drop anything whose absolute `ndx` was already emitted then.
+                        items.retain(|(_, message)| !prev_ndxs.contains(&message.common.ndx));
+                        prev_ndxs = items.iter().map(|(_, message)| message.common.ndx).collect();
+
                         for (_, message) in &items {
                             match message.specific {
                                 MessageSpecific::AircraftIdenAndCat { .. } => stat_aiac += 1,
@@ -1013,8 +2115,7 @@ fn main() {
                             match net_raw_out_stream {
                                 None => (),
                                 Some(ref mut stream) => {
-                                    let msg = message.common.msg.clone();
-                                    let hex_string: String = msg.iter().map(
+                                    let hex_string: String = message.common.msg.iter().map(
                                         |byte| format!("{:02X}", byte)
                                     ).collect();
                                     let line = format!("*{};\n", hex_string);
@@ -1025,17 +2126,23 @@ fn main() {
                                 },
                             }
 
+                            // Serve the message out on whichever of --beast-out, --avr-out,
+                            // --sbs-out and --tsv-out the user has configured. `entities`
+                            // still holds the prior cycle's tracked state for this addr,
+                            // which is all SBS needs for fields this message doesn't carry.
+                            let entity = netout::addr_of(message).and_then(|addr| entities.get(&addr));
+                            net_output.emit(message, entity);
+
                             // This is used when the --file-output argument is specified. It writes the
-                            // raw messages and associated data to a file in a serialized format. See
-                            // the function `write_message_to_file` for a detailed overview of the
-                            // format used.
+                            // message to a `capture` file (see that module), which `--replay` can
+                            // later read back and feed through this same pipeline.
                             match message.specific {
                                 MessageSpecific::Other => (),
                                 _ => {
-                                    match &mut file {
+                                    match &mut capture_writer {
                                         None => (),
-                                        Some(file) => {
-                                            write_message_to_file(file, &message);
+                                        Some(writer) => {
+                                            writer.write_message(&message).unwrap();
                                         },
                                     }
                                 },
@@ -1051,9 +2158,61 @@ fn main() {
                             sample_index,
                             &mut pipe_mgmt,
                             args.snr_scaler,
-                            args.weighted_avg_depth
+                            args.weighted_avg_depth,
+                            args.track_history_depth,
+                            args.track_max_speed_knots,
+                            args.track_history_timeout_secs,
+                            args.ref_lat,
+                            args.ref_lon
                         );
 
+                        // Drain whatever the upstream feeds have sent since the last cycle
+                        // and run it through the same decode/tracking pipeline. Their `ndx`
+                        // is already an absolute timestamp/counter, not a buffer offset, so
+                        // we process them with a zero base rather than `sample_index`.
+                        let mut upstream_items: Vec<(u64, Message)> = Vec::new();
+                        for upstream_rx in &upstream_rxs {
+                            while let Ok(result) = upstream_rx.try_recv() {
+                                match process_result(result, upstream_bit_error_table, &seen, args.aggressive_crc_fix) {
+                                    Ok(message) => upstream_items.push((message.common.ndx, message)),
+                                    Err(_) => (),
+                                }
+                            }
+                        }
+
+                        if !upstream_items.is_empty() {
+                            process_messages(
+                                upstream_items,
+                                &mut entities,
+                                0,
+                                &mut pipe_mgmt,
+                                args.snr_scaler,
+                                args.weighted_avg_depth,
+                                args.track_history_depth,
+                                args.track_max_speed_knots,
+                                args.track_history_timeout_secs,
+                                args.ref_lat,
+                                args.ref_lon
+                            );
+                        }
+
+                        // Drain per-pipe feedback and free any locked pipe whose aircraft
+                        // has gone quiet so it can go back to random search or be handed
+                        // to a stronger new contact.
+                        for (pipe_ndx, report) in pipe_mgmt.poll_feedback() {
+                            if report.decode_count == 0 {
+                                if let Some(addr) = pipe_mgmt.addr_for_pipe(pipe_ndx) {
+                                    pipe_mgmt.unset_addr(addr);
+                                }
+                            }
+                        }
+
+                        // Release any pipe whose aircraft hasn't been heard from in a
+                        // while so the finite pipe pool stays available for active traffic.
+                        for addr in pipe_mgmt.reap_idle(Instant::now()) {
+                            println!("released idle pipe for addr {:6x}", addr);
+                        }
+
                         if (Instant::now() - stat_start).as_secs() > 5 {
                             stat_start = Instant::now();
                             let elapsed_dur: Duration = stat_gstart.elapsed();
@@ -1089,13 +2248,23 @@ fn main() {
                                     Some(v) => v.into_iter().collect::<String>(),
                                 };
 
+                                // The smoothed position averages over recent history instead
+                                // of reporting the single latest (possibly glitchy) fix, same
+                                // as `push_position_fix`'s outlier rejection already leans on
+                                // that history; falls back to the raw last-known fix before
+                                // enough history has built up to smooth anything.
+                                let (lat, lon, alt) = match ent.smoothed_position() {
+                                    Some((lat, lon, alt)) => (lat, lon, alt.or(ent.alt)),
+                                    None => (ent.lat.unwrap_or(0.0), ent.lon.unwrap_or(0.0), ent.alt),
+                                };
+
                                 println!(
                                     "{:6x} {:>8} {:>8.1} {:>10.4} {:>10.4} {:0>7} {:>7} {:?}",
                                     addr,
                                     flight,
-                                    ent.alt.unwrap_or(0.0),
-                                    ent.lat.unwrap_or(0.0),
-                                    ent.lon.unwrap_or(0.0),
+                                    alt.unwrap_or(0.0),
+                                    lat,
+                                    lon,
                                     ent.message_count,
                                     // How many messages were picked from the calculated steering vector.
                                     ent.inbeam,
@@ -1121,13 +2290,21 @@ fn main() {
                             }
                         }
 
-                        sample_index += buffer.len() as u64 / (streams * 4) as u64;
-                        read = 0;
+                        // Only the newly-read portion counts toward the running sample
+                        // index; the carried-over overlap was already accounted for
+                        // when it was first read in as part of the previous cycle.
+                        sample_index += (buffer_len - overlap_bytes) as u64 / (streams * 4) as u64;
+                        read = overlap_bytes;
                     }
 
-                    true
+                    !shutdown.load(Ordering::Relaxed)
                 },
                 Ok(_) => false,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock || e.kind() == std::io::ErrorKind::TimedOut => {
+                    // Just the read timeout firing with nothing to read yet; loop back
+                    // around so we keep checking `shutdown` instead of blocking forever.
+                    !shutdown.load(Ordering::Relaxed)
+                },
                 Err(e) => {
                     eprintln!("error: {}", e);
                     false
@@ -1137,8 +2314,8 @@ fn main() {
             }
             
         },
-        Err(e) =>  {
-            eprintln!("failed to connect: {}", e);
+        None => {
+            println!("shutdown requested before connecting to the sample stream");
         }
     }
 
@@ -0,0 +1,139 @@
+//! MVDR (Minimum Variance Distortionless Response) adaptive beamforming.
+//!
+//! The conventional path in `stream.rs` always forms a steering vector from a fixed
+//! `theta` and simply sums the rotated antenna streams. That suppresses a desired
+//! weak aircraft whenever a strong co-channel emitter lines up with it. This module
+//! instead accumulates a running spatial covariance estimate over a sliding window
+//! of complex samples (one complex value per antenna) before computing weights,
+//! mirroring the "average the incoming data in time before doing any work on it"
+//! approach: `R = (1/N) sum x[n] x[n]^H`. For a candidate steering vector `a(theta)`
+//! it then computes `w = R^-1 a / (a^H R^-1 a)`, which places a null on interferers
+//! while preserving unit gain toward `theta`. Only the 2-antenna case is supported
+//! for now, matching the `process_buffer_single_x2` conventional path it backs up.
+
+use std::collections::VecDeque;
+
+/// A complex number stored as (real, imag), matching the `.cos()`/`.sin()` rotation
+/// style already used for steering vectors in `stream.rs`.
+pub type Complex = (f32, f32);
+
+fn c_mul(a: Complex, b: Complex) -> Complex {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn c_conj(a: Complex) -> Complex {
+    (a.0, -a.1)
+}
+
+fn c_add(a: Complex, b: Complex) -> Complex {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn c_scale(a: Complex, s: f32) -> Complex {
+    (a.0 * s, a.1 * s)
+}
+
+/// A 2x2 Hermitian spatial covariance matrix, accumulated over a sliding window of
+/// 2-antenna complex samples `x[n]`.
+///
+/// Stored as `r00`/`r11` (real antenna powers) and `r01` (the complex cross term;
+/// `r10` is its conjugate, so it isn't stored separately).
+pub struct Covariance {
+    r00: f32,
+    r11: f32,
+    r01: Complex,
+    window: VecDeque<(Complex, Complex)>,
+    window_len: usize,
+}
+
+impl Covariance {
+    /// `window_len` is the sliding window length (in samples) the covariance
+    /// estimate is averaged over.
+    pub fn new(window_len: usize) -> Covariance {
+        Covariance {
+            r00: 0.0,
+            r11: 0.0,
+            r01: (0.0, 0.0),
+            window: VecDeque::with_capacity(window_len),
+            window_len,
+        }
+    }
+
+    /// Folds one new 2-antenna sample `(x0, x1)` into the running window, evicting
+    /// the oldest sample's contribution once the window is full.
+    pub fn push(&mut self, x0: Complex, x1: Complex) {
+        if self.window.len() == self.window_len {
+            if let Some((old0, old1)) = self.window.pop_front() {
+                self.r00 -= old0.0 * old0.0 + old0.1 * old0.1;
+                self.r11 -= old1.0 * old1.0 + old1.1 * old1.1;
+                let cross = c_mul(old0, c_conj(old1));
+                self.r01 = (self.r01.0 - cross.0, self.r01.1 - cross.1);
+            }
+        }
+
+        self.r00 += x0.0 * x0.0 + x0.1 * x0.1;
+        self.r11 += x1.0 * x1.0 + x1.1 * x1.1;
+        let cross = c_mul(x0, c_conj(x1));
+        self.r01 = (self.r01.0 + cross.0, self.r01.1 + cross.1);
+
+        self.window.push_back((x0, x1));
+    }
+
+    /// Computes MVDR weights `w = R^-1 a / (a^H R^-1 a)` for the steering vector
+    /// `a = (1, exp(i*theta))`, with diagonal loading `R += loading_fraction *
+    /// trace(R) * I` to keep `R` invertible when the window is short or the signals
+    /// are strongly correlated. Returns `None` (so the caller can fall back to the
+    /// conventional beamformer) if the window hasn't filled yet or `R` is still
+    /// singular after loading.
+    pub fn mvdr_weights(&self, theta: f32, loading_fraction: f32) -> Option<(Complex, Complex)> {
+        if self.window.len() < self.window_len {
+            return None;
+        }
+
+        let n = self.window.len() as f32;
+        let trace = (self.r00 + self.r11) / n;
+        let loading = loading_fraction * trace;
+
+        let r00 = self.r00 / n + loading;
+        let r11 = self.r11 / n + loading;
+        let r01 = c_scale(self.r01, 1.0 / n);
+        let r10 = c_conj(r01);
+
+        // R is Hermitian positive semi-definite, so its determinant is real.
+        let det = r00 * r11 - (r01.0 * r10.0 - r01.1 * r10.1);
+
+        if det.abs() < 1e-12 {
+            return None;
+        }
+
+        // R^-1 = (1/det) * [ r11, -r01 ; -r10, r00 ]
+        let inv00 = r11 / det;
+        let inv11 = r00 / det;
+        let inv01 = c_scale(r01, -1.0 / det);
+        let inv10 = c_scale(r10, -1.0 / det);
+
+        // `apply_weights` computes y = w^H x, so the steering vector here must be
+        // conjugated relative to the e^{+iθ} rotation `process_buffer_single_x2`
+        // and the MVDR fallback branch in `stream.rs` apply to antenna B, or the
+        // distortionless constraint preserves unit gain toward -θ instead of θ.
+        let a0: Complex = (1.0, 0.0);
+        let a1: Complex = (theta.cos(), -theta.sin());
+
+        let ra0 = c_add(c_scale(a0, inv00), c_mul(inv01, a1));
+        let ra1 = c_add(c_mul(inv10, a0), c_scale(a1, inv11));
+
+        // a^H R^-1 a is real for Hermitian R.
+        let denom = c_mul(c_conj(a0), ra0).0 + c_mul(c_conj(a1), ra1).0;
+
+        if denom.abs() < 1e-12 {
+            return None;
+        }
+
+        Some((c_scale(ra0, 1.0 / denom), c_scale(ra1, 1.0 / denom)))
+    }
+}
+
+/// Applies weights `w` to a 2-antenna sample: `y[n] = w^H x[n]`.
+pub fn apply_weights(w: (Complex, Complex), x0: Complex, x1: Complex) -> Complex {
+    c_add(c_mul(c_conj(w.0), x0), c_mul(c_conj(w.1), x1))
+}
@@ -0,0 +1,186 @@
+//! Network input subsystem.
+//!
+//! The mirror of `netout`: connects to upstream Mode S receivers speaking Beast
+//! binary or AVR raw and turns their frames into `stream::ProcessStreamResult`s so
+//! they can run through the same `process_result`/`process_messages` pipeline used
+//! for our own locally demodulated samples. This lets the beamforming receiver
+//! cross-check its detections against a conventional omnidirectional feed.
+
+use std::io::{BufRead, BufReader, Read};
+use std::net::TcpStream;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::stream::ProcessStreamResult;
+
+/// Which framing an upstream connection speaks.
+#[derive(Debug, Clone, Copy)]
+pub enum UpstreamFormat {
+    Beast,
+    Avr,
+}
+
+/// A `pipe_ndx` no real pipe will ever have, so upstream messages never get counted
+/// as "in beam" by `Entity::check_if_in_beam` — they're ground truth, not a hit on
+/// our own steering vector.
+pub const UPSTREAM_PIPE_NDX: usize = usize::MAX;
+
+fn read_byte(reader: &mut impl Read) -> std::io::Result<u8> {
+    let mut b = [0u8; 1];
+    reader.read_exact(&mut b)?;
+    Ok(b[0])
+}
+
+/// Reads `payload_len` de-escaped bytes following a Beast frame marker and type
+/// byte, un-doubling any `0x1a` along the way.
+fn read_beast_payload(reader: &mut impl Read, payload_len: usize) -> std::io::Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(payload_len);
+
+    while out.len() < payload_len {
+        let b = read_byte(reader)?;
+        if b == 0x1a {
+            let next = read_byte(reader)?;
+            if next != 0x1a {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "beast frame truncated at escape"));
+            }
+        }
+        out.push(b);
+    }
+
+    Ok(out)
+}
+
+/// Reads one Beast binary frame: the `0x1a` marker, type byte, 6-byte MLAT
+/// timestamp, 1-byte signal level, then the raw message bytes.
+///
+/// Returns `Ok(None)` for a recognized-but-unsupported frame type (e.g. Mode AC)
+/// rather than an error, since those are routine on a live feed.
+fn read_beast_message(reader: &mut impl Read) -> std::io::Result<Option<ProcessStreamResult>> {
+    let mut b = read_byte(reader)?;
+    while b != 0x1a {
+        b = read_byte(reader)?;
+    }
+
+    let msg_len = match read_byte(reader)? {
+        0x32 => 7,
+        0x33 => 14,
+        _ => return Ok(None),
+    };
+
+    let payload = read_beast_payload(reader, 6 + 1 + msg_len)?;
+    let ndx = u64::from_be_bytes([
+        0, 0, payload[0], payload[1], payload[2], payload[3], payload[4], payload[5],
+    ]);
+    let snr = payload[6] as f32 / 255.0;
+    let msg = payload[7..].to_vec();
+
+    Ok(Some(ProcessStreamResult {
+        snr,
+        msg,
+        samples: Vec::new(),
+        ndx: ndx as usize,
+        thetas: Vec::new(),
+        amplitudes: Vec::new(),
+        pipe_ndx: UPSTREAM_PIPE_NDX,
+    }))
+}
+
+/// Reads one AVR raw line: `*<hex>;` or, with an MLAT timestamp prefix,
+/// `@<timestamphex><hex>;`. `counter` stands in for a timestamp on feeds that don't
+/// send one, so every upstream message still gets a distinct, increasing `ndx`.
+fn read_avr_message(reader: &mut impl BufRead, counter: &mut u64) -> std::io::Result<Option<ProcessStreamResult>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "avr stream closed"));
+    }
+
+    let line = line.trim();
+
+    let (ndx, hex) = if let Some(rest) = line.strip_prefix('@') {
+        if rest.len() <= 12 {
+            return Ok(None);
+        }
+        let ts = u64::from_str_radix(&rest[..12], 16).unwrap_or(0);
+        (ts, rest[12..].trim_end_matches(';'))
+    } else if let Some(rest) = line.strip_prefix('*') {
+        *counter += 1;
+        (*counter, rest.trim_end_matches(';'))
+    } else {
+        return Ok(None);
+    };
+
+    if hex.len() < 2 || hex.len() % 2 != 0 {
+        return Ok(None);
+    }
+
+    let mut msg = Vec::with_capacity(hex.len() / 2);
+    for i in (0..hex.len()).step_by(2) {
+        match u8::from_str_radix(&hex[i..i + 2], 16) {
+            Ok(byte) => msg.push(byte),
+            Err(_) => return Ok(None),
+        }
+    }
+
+    Ok(Some(ProcessStreamResult {
+        snr: 0.0,
+        msg,
+        samples: Vec::new(),
+        ndx: ndx as usize,
+        thetas: Vec::new(),
+        amplitudes: Vec::new(),
+        pipe_ndx: UPSTREAM_PIPE_NDX,
+    }))
+}
+
+/// Reads frames from `stream` in the given `format` and sends them to `tx` until
+/// the connection drops or the receiving end hangs up.
+fn pump(stream: TcpStream, format: UpstreamFormat, tx: &Sender<ProcessStreamResult>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+
+    match format {
+        UpstreamFormat::Beast => loop {
+            if let Some(result) = read_beast_message(&mut reader)? {
+                if tx.send(result).is_err() {
+                    return Ok(());
+                }
+            }
+        },
+        UpstreamFormat::Avr => {
+            let mut counter = 0u64;
+            loop {
+                if let Some(result) = read_avr_message(&mut reader, &mut counter)? {
+                    if tx.send(result).is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+        },
+    }
+}
+
+/// Connects to `addr`, reconnecting every 5 seconds if the connection drops, and
+/// returns a channel of decoded `ProcessStreamResult`s the caller can drain
+/// alongside its locally demodulated ones.
+pub fn connect(addr: &str, format: UpstreamFormat) -> Receiver<ProcessStreamResult> {
+    let (tx, rx) = channel();
+    let addr = addr.to_string();
+
+    thread::spawn(move || loop {
+        match TcpStream::connect(&addr) {
+            Ok(stream) => {
+                println!("connected to upstream {} ({:?})", addr, format);
+                if let Err(e) = pump(stream, format, &tx) {
+                    println!("upstream {} disconnected: {}", addr, e);
+                }
+            },
+            Err(e) => {
+                println!("failed to connect to upstream {}: {}", addr, e);
+            },
+        }
+
+        thread::sleep(Duration::from_secs(5));
+    });
+
+    rx
+}
@@ -0,0 +1,438 @@
+//! Network output subsystem.
+//!
+//! Serves decoded messages to TCP clients in the feed formats the existing ADS-B
+//! ecosystem (tar1090, FlightAware, VRS, ...) already knows how to consume: Beast
+//! binary, AVR raw hex, SBS-1 "BaseStation" CSV, and a simple TSV for ad-hoc tooling.
+//! Each format is served on its own port and accepts any number of clients; a client
+//! that falls behind is dropped rather than allowed to stall the decode thread.
+
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::{Entity, Message, MessageSpecific};
+
+/// Accepts any number of clients on a TCP port and fans out byte records to all of
+/// them, dropping a client once its outbound queue backs up instead of blocking.
+pub struct NetOutputServer {
+    senders: Arc<Mutex<Vec<SyncSender<Vec<u8>>>>>,
+}
+
+impl NetOutputServer {
+    /// Binds `addr` and spawns an accept loop. `client_queue_depth` bounds how many
+    /// pending records a client may have buffered before it is considered slow.
+    pub fn bind(addr: &str, client_queue_depth: usize) -> std::io::Result<NetOutputServer> {
+        let listener = TcpListener::bind(addr)?;
+        let senders: Arc<Mutex<Vec<SyncSender<Vec<u8>>>>> = Arc::new(Mutex::new(Vec::new()));
+        let accept_senders = senders.clone();
+
+        thread::spawn(move || {
+            for incoming in listener.incoming() {
+                match incoming {
+                    Ok(mut stream) => {
+                        let (tx, rx) = sync_channel::<Vec<u8>>(client_queue_depth);
+                        accept_senders.lock().unwrap().push(tx);
+
+                        thread::spawn(move || {
+                            while let Ok(bytes) = rx.recv() {
+                                if stream.write_all(&bytes).is_err() {
+                                    break;
+                                }
+                            }
+                        });
+                    },
+                    Err(_) => (),
+                }
+            }
+        });
+
+        Ok(NetOutputServer { senders })
+    }
+
+    /// Spawns a background thread that rebroadcasts `bytes` on `interval` for the
+    /// rest of the process's life (the thread outlives this server), so a client that isn't seeing any decoded
+    /// traffic still gets something on the wire every so often. Some routers
+    /// between a feeder and its consumer will silently drop a TCP connection
+    /// that's gone idle for too long.
+    pub fn spawn_heartbeat(&self, bytes: Vec<u8>, interval: Duration) {
+        let senders = self.senders.clone();
+
+        thread::spawn(move || {
+            loop {
+                thread::sleep(interval);
+
+                let mut senders = senders.lock().unwrap();
+                senders.retain(|tx| {
+                    match tx.try_send(bytes.clone()) {
+                        Ok(()) => true,
+                        Err(TrySendError::Full(_)) => true,
+                        Err(TrySendError::Disconnected(_)) => false,
+                    }
+                });
+            }
+        });
+    }
+
+    /// Sends `bytes` to every connected client.
+    ///
+    /// Uses `try_send` against each client's bounded queue: a client whose queue is
+    /// still full from previous records, or whose writer thread has hung up, is
+    /// dropped from the list entirely (its writer thread then drains what is queued
+    /// and closes the connection). Either way the decode thread never blocks on a
+    /// slow or dead reader.
+    pub fn broadcast(&self, bytes: &[u8]) {
+        let mut senders = self.senders.lock().unwrap();
+        senders.retain(|tx| {
+            match tx.try_send(bytes.to_vec()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => false,
+                Err(TrySendError::Disconnected(_)) => false,
+            }
+        });
+    }
+}
+
+/// Doubles every `0x1a` byte in `bytes`, as Beast binary framing requires for any
+/// byte that isn't the leading frame marker.
+fn beast_escape(bytes: &[u8], out: &mut Vec<u8>) {
+    for &b in bytes {
+        if b == 0x1a {
+            out.push(0x1a);
+        }
+        out.push(b);
+    }
+}
+
+/// Beast's MLAT timestamp ticks at 12 MHz; we sample at 2 MHz, so each sample index
+/// is worth this many Beast ticks.
+const BEAST_CLOCK_SCALE: u64 = 12_000_000 / 2_000_000;
+
+/// A Beast "null packet" heartbeat: a framed Mode S short frame (`0x1a 0x32`, zero
+/// timestamp, zero signal) whose seven message bytes are all zero, so it carries an
+/// invalid CRC/ICAO that every consumer just discards. Sent on an idle Beast feed so
+/// routers between here and the consumer don't time out the connection.
+pub const BEAST_HEARTBEAT: [u8; 16] = [
+    0x1a, 0x32,
+    0, 0, 0, 0, 0, 0,
+    0,
+    0, 0, 0, 0, 0, 0, 0,
+];
+
+/// Encodes a decoded message as a Beast binary frame.
+///
+/// `0x1a`, a type byte (`0x32` for a 7-byte short Mode S frame, `0x33` for 14-byte
+/// long), a 6-byte big-endian MLAT timestamp derived from `common.ndx` (the sample
+/// index scaled to Beast's 12 MHz clock and masked to 48 bits), a 1-byte signal level
+/// scaled from `common.snr`, then the raw message bytes. Every `0x1a` inside the
+/// timestamp/signal/payload is escaped by doubling it.
+pub fn encode_beast(m: &Message) -> Vec<u8> {
+    let mut out = Vec::with_capacity(2 + 6 + 1 + m.common.msg.len());
+    out.push(0x1a);
+    out.push(if m.common.msg.len() <= 7 { 0x32 } else { 0x33 });
+
+    let timestamp_12mhz: u64 = (m.common.ndx.wrapping_mul(BEAST_CLOCK_SCALE)) & 0xffff_ffff_ffff;
+    let timestamp_bytes = timestamp_12mhz.to_be_bytes();
+    beast_escape(&timestamp_bytes[2..8], &mut out);
+
+    let signal_byte = (m.common.snr.max(0.0).min(1.0) * 255.0) as u8;
+    beast_escape(&[signal_byte], &mut out);
+
+    beast_escape(&m.common.msg, &mut out);
+
+    out
+}
+
+/// Encodes a decoded message as AVR raw: `*<hexbytes>;\n`.
+pub fn encode_avr(m: &Message) -> String {
+    let hex: String = m.common.msg.iter().map(|b| format!("{:02X}", b)).collect();
+    format!("*{};\n", hex)
+}
+
+/// Encodes a decoded message as AVR raw with an MLAT timestamp prefix:
+/// `@<timestamphex><hexbytes>;\n`.
+pub fn encode_avr_mlat(m: &Message) -> String {
+    let timestamp_12mhz: u64 = (m.common.ndx.wrapping_mul(6)) & 0xffff_ffff_ffff;
+    let hex: String = m.common.msg.iter().map(|b| format!("{:02X}", b)).collect();
+    format!("@{:012X}{};\n", timestamp_12mhz, hex)
+}
+
+/// Returns the transponder address a message is about, if it has one.
+///
+/// `MessageSpecific::Other` carries no header, so there's nothing to key an
+/// `Entity` lookup by.
+pub fn addr_of(m: &Message) -> Option<u32> {
+    match &m.specific {
+        MessageSpecific::AircraftIdenAndCat { hdr, .. } => Some(hdr.addr),
+        MessageSpecific::SurfacePositionMessage { hdr, .. } => Some(hdr.addr),
+        MessageSpecific::AirbornePositionMessage { hdr, .. } => Some(hdr.addr),
+        MessageSpecific::AirborneVelocityMessage { hdr, .. } => Some(hdr.addr),
+        MessageSpecific::AirborneVelocityMessageShort { hdr, .. } => Some(hdr.addr),
+        MessageSpecific::ShortAirToAirSurveillance { hdr, .. } => Some(hdr.addr),
+        MessageSpecific::SurveillanceAltitudeReply { hdr, .. } => Some(hdr.addr),
+        MessageSpecific::SurveillanceIdentityReply { hdr, .. } => Some(hdr.addr),
+        MessageSpecific::LongAirToAirSurveillance { hdr, .. } => Some(hdr.addr),
+        MessageSpecific::CommBAltitudeReply { hdr, .. } => Some(hdr.addr),
+        MessageSpecific::CommBIdentityReply { hdr, .. } => Some(hdr.addr),
+        MessageSpecific::AircraftStatus { hdr, .. } => Some(hdr.addr),
+        MessageSpecific::TargetStateAndStatus { hdr, .. } => Some(hdr.addr),
+        MessageSpecific::OperationalStatus { hdr, .. } => Some(hdr.addr),
+        MessageSpecific::Other => None,
+    }
+}
+
+/// Encodes a decoded message plus its aircraft's known state as an SBS-1
+/// "BaseStation" CSV row, terminated with `\r\n` as the format expects.
+///
+/// `entity` supplies the fields `process_result` doesn't itself carry (callsign,
+/// lat/lon, velocity) once they've been tracked for this address.
+pub fn encode_sbs(m: &Message, entity: Option<&Entity>) -> String {
+    let addr = match &m.specific {
+        MessageSpecific::AircraftIdenAndCat { hdr, .. } => hdr.addr,
+        MessageSpecific::SurfacePositionMessage { hdr, .. } => hdr.addr,
+        MessageSpecific::AirbornePositionMessage { hdr, .. } => hdr.addr,
+        MessageSpecific::AirborneVelocityMessage { hdr, .. } => hdr.addr,
+        MessageSpecific::AirborneVelocityMessageShort { hdr, .. } => hdr.addr,
+        MessageSpecific::ShortAirToAirSurveillance { hdr, .. } => hdr.addr,
+        MessageSpecific::SurveillanceAltitudeReply { hdr, .. } => hdr.addr,
+        MessageSpecific::SurveillanceIdentityReply { hdr, .. } => hdr.addr,
+        MessageSpecific::LongAirToAirSurveillance { hdr, .. } => hdr.addr,
+        MessageSpecific::CommBAltitudeReply { hdr, .. } => hdr.addr,
+        MessageSpecific::CommBIdentityReply { hdr, .. } => hdr.addr,
+        MessageSpecific::AircraftStatus { hdr, .. } => hdr.addr,
+        MessageSpecific::TargetStateAndStatus { hdr, .. } => hdr.addr,
+        MessageSpecific::OperationalStatus { hdr, .. } => hdr.addr,
+        MessageSpecific::Other => 0,
+    };
+
+    let transmission_type = match &m.specific {
+        MessageSpecific::AircraftIdenAndCat { .. } => 1,
+        MessageSpecific::SurfacePositionMessage { .. } => 2,
+        MessageSpecific::AirbornePositionMessage { .. } => 3,
+        MessageSpecific::AirborneVelocityMessage { .. } => 4,
+        MessageSpecific::AirborneVelocityMessageShort { .. } => 4,
+        MessageSpecific::ShortAirToAirSurveillance { .. } => 5,
+        MessageSpecific::SurveillanceAltitudeReply { .. } => 5,
+        MessageSpecific::LongAirToAirSurveillance { .. } => 5,
+        MessageSpecific::CommBAltitudeReply { .. } => 5,
+        MessageSpecific::SurveillanceIdentityReply { .. } => 6,
+        MessageSpecific::CommBIdentityReply { .. } => 6,
+        MessageSpecific::AircraftStatus { .. } => 8,
+        MessageSpecific::TargetStateAndStatus { .. } => 8,
+        MessageSpecific::OperationalStatus { .. } => 8,
+        MessageSpecific::Other => 8,
+    };
+
+    let flight = match &m.specific {
+        MessageSpecific::AircraftIdenAndCat { flight, .. } => {
+            flight.iter().collect::<String>().trim().to_string()
+        },
+        _ => entity.and_then(|e| e.flight.as_ref()).map(|f| f.iter().collect::<String>().trim().to_string()).unwrap_or_default(),
+    };
+
+    let altitude = match &m.specific {
+        MessageSpecific::AirbornePositionMessage { altitude, .. } => *altitude,
+        MessageSpecific::ShortAirToAirSurveillance { altitude, .. } => *altitude,
+        MessageSpecific::SurveillanceAltitudeReply { altitude, .. } => *altitude,
+        MessageSpecific::LongAirToAirSurveillance { altitude, .. } => *altitude,
+        MessageSpecific::CommBAltitudeReply { altitude, .. } => *altitude,
+        _ => entity.and_then(|e| e.alt).unwrap_or(0.0),
+    };
+
+    let (lat, lon) = entity.map(|e| (e.lat.unwrap_or(0.0), e.lon.unwrap_or(0.0))).unwrap_or((0.0, 0.0));
+
+    let (ground_speed, track) = match &m.specific {
+        MessageSpecific::AirborneVelocityMessage { velocity, heading, .. } => (*velocity, *heading),
+        MessageSpecific::AirborneVelocityMessageShort { heading, .. } => (entity.and_then(|e| e.velocity).unwrap_or(0.0), *heading),
+        _ => (entity.and_then(|e| e.velocity).unwrap_or(0.0), 0.0),
+    };
+
+    let squawk = match &m.specific {
+        MessageSpecific::AircraftIdenAndCat { hdr, .. } => hdr.identity,
+        MessageSpecific::SurveillanceIdentityReply { hdr, .. } => hdr.identity,
+        MessageSpecific::CommBIdentityReply { hdr, .. } => hdr.identity,
+        MessageSpecific::AircraftStatus { squawk, .. } => *squawk,
+        _ => 0,
+    };
+
+    // We don't track BaseStation sessions or distinct feed sources, so session_id and
+    // aircraft_id are always this receiver's single implicit one.
+    const SESSION_ID: u32 = 1;
+    const AIRCRAFT_ID: u32 = 1;
+
+    // MSG,transmission_type,session_id,aircraft_id,hex_ident,flight_id,date_gen,
+    // time_gen,date_logged,time_logged,callsign,altitude,ground_speed,track,lat,
+    // lon,vertical_rate,squawk,alert,emergency,spi,is_on_ground
+    format!(
+        "MSG,{},{},{},{:06X},{},,,,,{},{},{},{},{},{},,{},0,0,0,0\r\n",
+        transmission_type,
+        SESSION_ID,
+        AIRCRAFT_ID,
+        addr,
+        AIRCRAFT_ID,
+        flight,
+        altitude,
+        ground_speed,
+        track,
+        lat,
+        lon,
+        squawk,
+    )
+}
+
+/// Encodes a decoded message as tab-separated `key\tvalue` records, one record per
+/// line, terminated with `\n`.
+pub fn encode_tsv(m: &Message) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("ndx\t{}\n", m.common.ndx));
+    out.push_str(&format!("snr\t{}\n", m.common.snr));
+    out.push_str(&format!("crc_ok\t{}\n", m.common.crc_ok));
+
+    match &m.specific {
+        MessageSpecific::AircraftIdenAndCat { hdr, aircraft_type, flight } => {
+            out.push_str(&format!("type\tAircraftIdenAndCat\n"));
+            out.push_str(&format!("addr\t{:06X}\n", hdr.addr));
+            out.push_str(&format!("aircraft_type\t{}\n", aircraft_type));
+            out.push_str(&format!("flight\t{}\n", flight.iter().collect::<String>()));
+        },
+        MessageSpecific::SurfacePositionMessage { hdr, .. } => {
+            out.push_str(&format!("type\tSurfacePositionMessage\n"));
+            out.push_str(&format!("addr\t{:06X}\n", hdr.addr));
+        },
+        MessageSpecific::AirbornePositionMessage { hdr, altitude, .. } => {
+            out.push_str(&format!("type\tAirbornePositionMessage\n"));
+            out.push_str(&format!("addr\t{:06X}\n", hdr.addr));
+            out.push_str(&format!("altitude\t{}\n", altitude));
+        },
+        MessageSpecific::AirborneVelocityMessage { hdr, velocity, heading, .. } => {
+            out.push_str(&format!("type\tAirborneVelocityMessage\n"));
+            out.push_str(&format!("addr\t{:06X}\n", hdr.addr));
+            out.push_str(&format!("velocity\t{}\n", velocity));
+            out.push_str(&format!("heading\t{}\n", heading));
+        },
+        MessageSpecific::AirborneVelocityMessageShort { hdr, heading } => {
+            out.push_str(&format!("type\tAirborneVelocityMessageShort\n"));
+            out.push_str(&format!("addr\t{:06X}\n", hdr.addr));
+            out.push_str(&format!("heading\t{}\n", heading));
+        },
+        MessageSpecific::ShortAirToAirSurveillance { hdr, vertical_status, altitude } => {
+            out.push_str(&format!("type\tShortAirToAirSurveillance\n"));
+            out.push_str(&format!("addr\t{:06X}\n", hdr.addr));
+            out.push_str(&format!("vertical_status\t{}\n", vertical_status));
+            out.push_str(&format!("altitude\t{}\n", altitude));
+        },
+        MessageSpecific::SurveillanceAltitudeReply { hdr, altitude } => {
+            out.push_str(&format!("type\tSurveillanceAltitudeReply\n"));
+            out.push_str(&format!("addr\t{:06X}\n", hdr.addr));
+            out.push_str(&format!("altitude\t{}\n", altitude));
+        },
+        MessageSpecific::SurveillanceIdentityReply { hdr } => {
+            out.push_str(&format!("type\tSurveillanceIdentityReply\n"));
+            out.push_str(&format!("addr\t{:06X}\n", hdr.addr));
+            out.push_str(&format!("squawk\t{}\n", hdr.identity));
+        },
+        MessageSpecific::LongAirToAirSurveillance { hdr, altitude, .. } => {
+            out.push_str(&format!("type\tLongAirToAirSurveillance\n"));
+            out.push_str(&format!("addr\t{:06X}\n", hdr.addr));
+            out.push_str(&format!("altitude\t{}\n", altitude));
+        },
+        MessageSpecific::CommBAltitudeReply { hdr, altitude, .. } => {
+            out.push_str(&format!("type\tCommBAltitudeReply\n"));
+            out.push_str(&format!("addr\t{:06X}\n", hdr.addr));
+            out.push_str(&format!("altitude\t{}\n", altitude));
+        },
+        MessageSpecific::CommBIdentityReply { hdr, .. } => {
+            out.push_str(&format!("type\tCommBIdentityReply\n"));
+            out.push_str(&format!("addr\t{:06X}\n", hdr.addr));
+            out.push_str(&format!("squawk\t{}\n", hdr.identity));
+        },
+        MessageSpecific::AircraftStatus { hdr, emergency_state, squawk } => {
+            out.push_str(&format!("type\tAircraftStatus\n"));
+            out.push_str(&format!("addr\t{:06X}\n", hdr.addr));
+            out.push_str(&format!("emergency_state\t{}\n", emergency_state));
+            out.push_str(&format!("squawk\t{}\n", squawk));
+        },
+        MessageSpecific::TargetStateAndStatus {
+            hdr,
+            selected_altitude,
+            barometric_setting,
+            selected_heading,
+            autopilot_engaged,
+            vnav_engaged,
+            altitude_hold_engaged,
+            lnav_engaged
+        } => {
+            out.push_str(&format!("type\tTargetStateAndStatus\n"));
+            out.push_str(&format!("addr\t{:06X}\n", hdr.addr));
+            out.push_str(&format!("selected_altitude\t{}\n", selected_altitude));
+            out.push_str(&format!("barometric_setting\t{}\n", barometric_setting));
+            out.push_str(&format!("selected_heading\t{}\n", selected_heading));
+            out.push_str(&format!("autopilot_engaged\t{}\n", autopilot_engaged));
+            out.push_str(&format!("vnav_engaged\t{}\n", vnav_engaged));
+            out.push_str(&format!("altitude_hold_engaged\t{}\n", altitude_hold_engaged));
+            out.push_str(&format!("lnav_engaged\t{}\n", lnav_engaged));
+        },
+        MessageSpecific::OperationalStatus { hdr, version, nic_supplement, nac_p, sil } => {
+            out.push_str(&format!("type\tOperationalStatus\n"));
+            out.push_str(&format!("addr\t{:06X}\n", hdr.addr));
+            out.push_str(&format!("version\t{}\n", version));
+            out.push_str(&format!("nic_supplement\t{}\n", nic_supplement));
+            out.push_str(&format!("nac_p\t{}\n", nac_p));
+            out.push_str(&format!("sil\t{}\n", sil));
+        },
+        MessageSpecific::Other => {
+            out.push_str("type\tOther\n");
+        },
+    }
+
+    // A record that landed exactly on a filled output buffer should never be allowed
+    // to lose its trailing newline (a known faup1090 breakage), so always terminate
+    // the whole record with one final blank line as an unambiguous boundary marker.
+    out.push('\n');
+    out
+}
+
+/// Bundles whichever output servers the user has enabled, so the decode loop has a
+/// single `emit` call regardless of which formats are configured.
+pub struct NetOutput {
+    pub beast: Option<NetOutputServer>,
+    pub avr: Option<NetOutputServer>,
+    pub sbs: Option<NetOutputServer>,
+    pub tsv: Option<NetOutputServer>,
+}
+
+impl NetOutput {
+    /// Binds a single output server, printing the error and panicking with a message
+    /// naming `addr` if the bind fails.
+    pub fn bind_or_panic(addr: &str, client_queue_depth: usize) -> NetOutputServer {
+        match NetOutputServer::bind(addr, client_queue_depth) {
+            Ok(server) => server,
+            Err(e) => {
+                println!("{}", e);
+                panic!("failed to bind net output server on {}", addr)
+            },
+        }
+    }
+
+    /// Encodes and broadcasts `m` (with its tracked `entity`, if any) to every
+    /// configured output server.
+    pub fn emit(&self, m: &Message, entity: Option<&Entity>) {
+        if let Some(server) = &self.beast {
+            server.broadcast(&encode_beast(m));
+        }
+
+        if let Some(server) = &self.avr {
+            server.broadcast(encode_avr(m).as_bytes());
+        }
+
+        if let Some(server) = &self.sbs {
+            server.broadcast(encode_sbs(m, entity).as_bytes());
+        }
+
+        if let Some(server) = &self.tsv {
+            server.broadcast(encode_tsv(m).as_bytes());
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! Adaptive FFT-based auto-notch filtering of narrowband interferers.
+//!
+//! A strong continuous-wave or narrowband emitter inside the 2 MHz band raises the
+//! noise floor and can fail the preamble test in `stream::process_stream_mfloat32`
+//! long before a message is ever decoded. Rather than a fixed notch filter (which
+//! can't track a drifting interferer and would need to be retuned by hand), this
+//! periodically takes an FFT over a block of the combined complex stream, picks the
+//! strongest bins, and tracks each one with a first-order IIR amplitude estimate:
+//! `gain_k += mu * (x[m] * conj(expj_k[m]) - gain_k)`, subtracting `gain_k *
+//! expj_k[m]` from every sample. `expj_k[m] = exp(j*2*pi*k*m/N)` is a constant-rate
+//! rotation, so rather than calling `cos`/`sin` for every sample, each slot keeps a
+//! running phasor advanced once per sample by multiplying by the per-bin rotation
+//! step `exp(j*2*pi*k/N)`, precomputed whenever a slot re-locks to a new bin.
+//!
+//! This only removes narrowband energy concentrated in a handful of bins; the wide
+//! energy of an ADS-B pulse is spread across the whole block and is left intact.
+
+use crate::mvdr::Complex;
+
+fn c_mul(a: Complex, b: Complex) -> Complex {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn c_conj(a: Complex) -> Complex {
+    (a.0, -a.1)
+}
+
+fn c_sub(a: Complex, b: Complex) -> Complex {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn c_scale(a: Complex, s: f32) -> Complex {
+    (a.0 * s, a.1 * s)
+}
+
+/// In-place iterative radix-2 Cooley-Tukey FFT. `data.len()` must be a power of two.
+fn fft_radix2(data: &mut [Complex]) {
+    let n = data.len();
+    if n <= 1 {
+        return;
+    }
+
+    // Bit-reversal permutation.
+    let mut j = 0usize;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j ^= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let ang = -2.0 * std::f32::consts::PI / len as f32;
+        let wlen: Complex = (ang.cos(), ang.sin());
+
+        let mut i = 0;
+        while i < n {
+            let mut w: Complex = (1.0, 0.0);
+            for k in 0..len / 2 {
+                let u = data[i + k];
+                let v = c_mul(data[i + k + len / 2], w);
+                data[i + k] = (u.0 + v.0, u.1 + v.1);
+                data[i + k + len / 2] = (u.0 - v.0, u.1 - v.1);
+                w = c_mul(w, wlen);
+            }
+            i += len;
+        }
+
+        len <<= 1;
+    }
+}
+
+/// One tracked narrowband bin: which FFT bin it's locked to, the running IIR
+/// amplitude/phase estimate `gain`, the running phasor `expj_k[m]` sampled once per
+/// input sample, and the per-sample rotation step that advances it.
+struct Slot {
+    bin: Option<usize>,
+    gain: Complex,
+    phasor: Complex,
+    step: Complex,
+}
+
+impl Slot {
+    fn empty() -> Slot {
+        Slot {
+            bin: None,
+            gain: (0.0, 0.0),
+            phasor: (1.0, 0.0),
+            step: (1.0, 0.0),
+        }
+    }
+
+    /// Re-locks this slot onto `bin` of an `n`-sample block, resetting the IIR
+    /// estimate and phasor since the old estimate no longer applies.
+    fn relock(&mut self, bin: usize, n: usize) {
+        self.bin = Some(bin);
+        self.gain = (0.0, 0.0);
+        self.phasor = (1.0, 0.0);
+        let ang = 2.0 * std::f32::consts::PI * bin as f32 / n as f32;
+        self.step = (ang.cos(), ang.sin());
+    }
+}
+
+/// Adaptive notch filter tracking the `n_slots` strongest narrowband bins over a
+/// block of `block_size` complex samples, re-detecting every `block_size` samples.
+pub struct AutoNotch {
+    block_size: usize,
+    mu: f32,
+    slots: Vec<Slot>,
+    block: Vec<Complex>,
+}
+
+impl AutoNotch {
+    /// `block_size` must be a power of two (the FFT used to detect bins is radix-2).
+    pub fn new(n_slots: usize, block_size: usize, mu: f32) -> AutoNotch {
+        assert!(block_size.is_power_of_two(), "notch block_size must be a power of two");
+
+        AutoNotch {
+            block_size,
+            mu,
+            slots: (0..n_slots).map(|_| Slot::empty()).collect(),
+            block: Vec::with_capacity(block_size),
+        }
+    }
+
+    /// Subtracts the current notch estimate from `sample`, advances each locked
+    /// slot's phasor and IIR gain, and folds `sample` into the block used for the
+    /// next bin re-detection. Returns the notched sample.
+    pub fn process(&mut self, sample: Complex) -> Complex {
+        self.block.push(sample);
+
+        let mut filtered = sample;
+
+        for slot in &mut self.slots {
+            if slot.bin.is_none() {
+                continue;
+            }
+
+            let estimate = c_mul(slot.gain, slot.phasor);
+            filtered = c_sub(filtered, estimate);
+
+            // gain_k += mu * (x[m] * conj(expj_k[m]) - gain_k)
+            let target = c_mul(sample, c_conj(slot.phasor));
+            slot.gain = (
+                slot.gain.0 + self.mu * (target.0 - slot.gain.0),
+                slot.gain.1 + self.mu * (target.1 - slot.gain.1),
+            );
+
+            slot.phasor = c_mul(slot.phasor, slot.step);
+        }
+
+        if self.block.len() == self.block_size {
+            self.redetect();
+            self.block.clear();
+        }
+
+        filtered
+    }
+
+    /// Runs an FFT over the just-completed block and re-locks each slot onto one of
+    /// the `n_slots` strongest bins. A slot whose detected bin is unchanged keeps
+    /// tracking (no IIR reset); a slot that moves to a new bin (or locks for the
+    /// first time) resets its gain so a stale estimate doesn't get applied to a
+    /// now-unrelated bin.
+    fn redetect(&mut self) {
+        let n = self.block.len();
+        let mut spectrum = self.block.clone();
+        fft_radix2(&mut spectrum);
+
+        let mut bins: Vec<usize> = (0..n).collect();
+        bins.sort_unstable_by(|&a, &b| {
+            let mag_a = spectrum[a].0 * spectrum[a].0 + spectrum[a].1 * spectrum[a].1;
+            let mag_b = spectrum[b].0 * spectrum[b].0 + spectrum[b].1 * spectrum[b].1;
+            mag_b.partial_cmp(&mag_a).unwrap()
+        });
+
+        for (slot, &bin) in self.slots.iter_mut().zip(bins.iter()) {
+            if slot.bin != Some(bin) {
+                slot.relock(bin, n);
+            }
+        }
+    }
+}
@@ -1,7 +1,10 @@
 //! This module implements a management layer for the pipes across multiple threads.
 
-use std::sync::mpsc::Sender;
+use std::sync::mpsc::{SyncSender, Receiver, TrySendError};
+use std::sync::Arc;
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use serde::{Serialize, Deserialize};
 
 /// Provides easy to use functions to manage the cycle/pipes across multiple threads.
 ///
@@ -9,12 +12,27 @@ use std::collections::HashMap;
 /// aircraft and assign a specific theta value for use in beamforming. It also allows one to
 /// disassociate a pipe when it is no longer needed causing it to run the standard algorithm
 /// which at this moment is a random search.
+///
+/// The channel to each worker thread is bounded (see `push_tx`). This means a worker that
+/// falls behind applies real backpressure to `set_pipe_to_theta`/`unset_addr` (the `send`
+/// call blocks) instead of an unbounded queue growing without limit. `send_buffer_to_all`
+/// is on the hot path and uses `try_send` instead so a single slow thread can't stall the
+/// whole cycle; it reports which threads were skipped so the caller can log or react.
 pub struct PipeManagement {
-    txs: Vec<Sender<ThreadTxMessage>>,
+    txs: Vec<SyncSender<ThreadTxMessage>>,
+    /// One feedback receiver per thread, paired with `txs` by index.
+    feedback_rxs: Vec<Receiver<ThreadRxMessage>>,
     /// Maps transponder address or u32 to a pipe.
     addr_to_pipe: HashMap<u32, (usize, usize)>,
     /// Maps each pipe to a transponder address or u32.
     pipe_to_addr: Vec<Option<u32>>,
+    /// Last theta vector sent for each assigned address, kept so state can be
+    /// snapshotted and restored across a restart (see `snapshot`/`restore`).
+    addr_to_theta: HashMap<u32, Vec<f32>>,
+    /// Last time each assigned address was seen, refreshed by callers via `touch_addr`.
+    addr_last_seen: HashMap<u32, Instant>,
+    /// How long an assigned address may go unseen before `reap_idle` releases its pipe.
+    idle_timeout: Duration,
     /// The total number of threads.
     thread_count: usize,
     /// The total number of pipes per thread.
@@ -28,8 +46,12 @@ impl PipeManagement {
     pub fn new(thread_count: usize, pipe_count: usize) -> PipeManagement {
         PipeManagement {
             txs: Vec::new(),
+            feedback_rxs: Vec::new(),
             addr_to_pipe: HashMap::new(),
             pipe_to_addr: vec![None; thread_count * pipe_count],
+            addr_to_theta: HashMap::new(),
+            addr_last_seen: HashMap::new(),
+            idle_timeout: Duration::from_secs(60),
             thread_count: thread_count,
             pipe_count: pipe_count,
         }
@@ -50,12 +72,45 @@ impl PipeManagement {
             None => (),
             Some((thread_ndx, pipe_ndx)) => {
                 self.addr_to_pipe.remove(&addr);
+                self.addr_to_theta.remove(&addr);
+                self.addr_last_seen.remove(&addr);
                 self.pipe_to_addr[thread_ndx * self.pipe_count + pipe_ndx] = None;
                 self.txs[thread_ndx].send(ThreadTxMessage::UnsetWeights(pipe_ndx)).unwrap();
             }
         }
     }
 
+    /// Sets how long an assigned address may go without a `touch_addr` call before
+    /// `reap_idle` releases its pipe back to random search.
+    pub fn set_idle_timeout(&mut self, timeout: Duration) {
+        self.idle_timeout = timeout;
+    }
+
+    /// Refreshes the last-seen time for an assigned address.
+    ///
+    /// Callers should call this whenever a new frame arrives for `addr` so `reap_idle`
+    /// doesn't release its pipe just because it hasn't been re-assigned a theta recently.
+    pub fn touch_addr(&mut self, addr: u32, now: Instant) {
+        if self.addr_to_pipe.contains_key(&addr) {
+            self.addr_last_seen.insert(addr, now);
+        }
+    }
+
+    /// Unassigns and returns every address whose pipe has gone unseen longer than
+    /// `idle_timeout`, reverting those pipes to random search.
+    pub fn reap_idle(&mut self, now: Instant) -> Vec<u32> {
+        let idle: Vec<u32> = self.addr_last_seen.iter()
+            .filter(|(_, last_seen)| now.duration_since(**last_seen) > self.idle_timeout)
+            .map(|(addr, _)| *addr)
+            .collect();
+
+        for addr in &idle {
+            self.unset_addr(*addr);
+        }
+
+        idle
+    }
+
     /// Sets pipe to theta using global pipe index (across all threads).
     ///
     /// If thetas is None this will unset the pipe and it will return to
@@ -69,15 +124,22 @@ impl PipeManagement {
         match thetas {
             Some(v) => {
                 self.pipe_to_addr[pipe_ndx] = Some(addr);
+                self.addr_to_theta.insert(addr, v.clone());
                 self.txs[thread_ndx].send(ThreadTxMessage::SetWeights(local_pipe_ndx, v)).unwrap();
             },
             None => {
                 self.pipe_to_addr[pipe_ndx] = None;
+                self.addr_to_theta.remove(&addr);
                 self.txs[thread_ndx].send(ThreadTxMessage::UnsetWeights(local_pipe_ndx)).unwrap();
             },
         }
     }
 
+    /// Looks up the address currently assigned to a global pipe index, if any.
+    pub fn addr_for_pipe(&self, pipe_ndx: usize) -> Option<u32> {
+        self.pipe_to_addr[pipe_ndx]
+    }
+
     pub fn get_addr_pipe_ndx(&self, addr: u32) -> Option<usize> {
         match self.addr_to_pipe.get(&addr) {
             None => None,
@@ -95,6 +157,7 @@ impl PipeManagement {
     ) -> bool {
         match self.addr_to_pipe.get(&addr) {
             Some((thread_ndx, pipe_ndx)) => {
+                self.addr_to_theta.insert(addr, thetas.clone());
                 self.txs[*thread_ndx].send(ThreadTxMessage::SetWeights(*pipe_ndx, thetas)).unwrap();
                 true
             },
@@ -105,6 +168,8 @@ impl PipeManagement {
                         let thread_ndx = x / self.pipe_count;
                         let pipe_ndx = x - thread_ndx * self.pipe_count;
                         self.addr_to_pipe.insert(addr, (thread_ndx, pipe_ndx));
+                        self.addr_to_theta.insert(addr, thetas.clone());
+                        self.addr_last_seen.insert(addr, Instant::now());
                         self.txs[thread_ndx].send(ThreadTxMessage::SetWeights(pipe_ndx, thetas)).unwrap();
                         return true;
                     }
@@ -115,27 +180,209 @@ impl PipeManagement {
     }
 
     /// Sends a buffer to all threads to be processed.
-    pub fn send_buffer_to_all(&self, buffer: &Vec<u8>, streams: usize) {
-        for tx in &self.txs {
-            tx.send(ThreadTxMessage::Buffer(buffer.clone(), streams)).unwrap();
+    ///
+    /// `buffer` is shared via `Arc` so every thread gets the same allocation and only
+    /// the refcount is bumped per thread, rather than cloning the whole sample buffer
+    /// once per thread as before.
+    ///
+    /// Uses `try_send` rather than a blocking `send` since this runs once per buffer
+    /// on the hot path: a thread that is still chewing on the previous buffer is
+    /// skipped instead of stalling every other thread. Returns `Ok(())` if every
+    /// thread accepted the buffer, or `Err(stalled)` with the thread indices that
+    /// were full or disconnected.
+    ///
+    /// `cycle` identifies this buffer so a worker's eventual result can be matched
+    /// back against the cycle it actually answers, not just whichever cycle happens
+    /// to be collecting when the result arrives.
+    pub fn send_buffer_to_all(&self, buffer: &Arc<Vec<u8>>, streams: usize, cycle: u64) -> Result<(), Vec<usize>> {
+        let mut stalled: Vec<usize> = Vec::new();
+
+        for (thread_ndx, tx) in self.txs.iter().enumerate() {
+            match tx.try_send(ThreadTxMessage::Buffer(buffer.clone(), streams, cycle)) {
+                Ok(()) => (),
+                Err(TrySendError::Full(_)) => stalled.push(thread_ndx),
+                Err(TrySendError::Disconnected(_)) => stalled.push(thread_ndx),
+            }
+        }
+
+        if stalled.is_empty() {
+            Ok(())
+        } else {
+            Err(stalled)
         }
     }
-    
+
     /// Used when this structure is first created.
-    pub fn push_tx(&mut self, sender: Sender<ThreadTxMessage>) {
+    ///
+    /// `sender` should come from `std::sync::mpsc::sync_channel` with a bound
+    /// sized to how many buffers a worker may lag behind before we start
+    /// applying backpressure. `feedback_rx` is the matching inbound half a
+    /// thread uses to report per-pipe quality back (see `poll_feedback`).
+    pub fn push_tx(&mut self, sender: SyncSender<ThreadTxMessage>, feedback_rx: Receiver<ThreadRxMessage>) {
         self.txs.push(sender);
+        self.feedback_rxs.push(feedback_rx);
+    }
+
+    /// Drains every pending feedback report from every thread without blocking.
+    ///
+    /// Each report is returned tagged with its global pipe index (across all threads)
+    /// so callers can tell, for example, that a pipe locked to an aircraft has gone
+    /// quiet and should be reassigned.
+    pub fn poll_feedback(&mut self) -> Vec<(usize, PipeReport)> {
+        let mut reports: Vec<(usize, PipeReport)> = Vec::new();
+
+        for (thread_ndx, rx) in self.feedback_rxs.iter().enumerate() {
+            loop {
+                match rx.try_recv() {
+                    Ok(ThreadRxMessage::Report(local_pipe_ndx, report)) => {
+                        reports.push((thread_ndx * self.pipe_count + local_pipe_ndx, report));
+                    },
+                    Err(_) => break,
+                }
+            }
+        }
+
+        reports
+    }
+
+    /// Captures the current address→pipe assignments and their last-known thetas.
+    pub fn snapshot(&self) -> PipeManagementSnapshot {
+        PipeManagementSnapshot {
+            addr_to_pipe: self.addr_to_pipe.clone(),
+            addr_to_theta: self.addr_to_theta.clone(),
+        }
+    }
+
+    /// Reapplies a previously captured snapshot, restoring each address to the pipe
+    /// index and theta it held when the snapshot was taken.
+    ///
+    /// An address whose recorded pipe is already taken by something else (e.g. the
+    /// thread/pipe count changed between runs) is skipped rather than stomping on it.
+    pub fn restore(&mut self, snapshot: PipeManagementSnapshot) {
+        for (addr, (thread_ndx, pipe_ndx)) in snapshot.addr_to_pipe {
+            if thread_ndx >= self.thread_count || pipe_ndx >= self.pipe_count {
+                continue;
+            }
+
+            let global_pipe_ndx = thread_ndx * self.pipe_count + pipe_ndx;
+
+            if self.pipe_to_addr[global_pipe_ndx].is_some() {
+                continue;
+            }
+
+            let thetas = match snapshot.addr_to_theta.get(&addr) {
+                Some(v) => v.clone(),
+                None => continue,
+            };
+
+            self.pipe_to_addr[global_pipe_ndx] = Some(addr);
+            self.addr_to_pipe.insert(addr, (thread_ndx, pipe_ndx));
+            self.addr_to_theta.insert(addr, thetas.clone());
+            self.txs[thread_ndx].send(ThreadTxMessage::SetWeights(pipe_ndx, thetas)).unwrap();
+        }
+    }
+
+    /// Applies a serializable `PipeCommand`, the mirror of the hand-called methods above.
+    ///
+    /// This is the entry point a control socket should deserialize commands into, so a
+    /// remote process can drive pipe assignments without bespoke glue per command.
+    pub fn apply_command(&mut self, cmd: PipeCommand) {
+        match cmd {
+            PipeCommand::SetAddrTheta(addr, thetas) => {
+                self.set_addr_to_theta(addr, thetas);
+            },
+            PipeCommand::UnsetAddr(addr) => {
+                self.unset_addr(addr);
+            },
+            PipeCommand::SetPipeTheta(pipe_ndx, addr, thetas) => {
+                self.set_pipe_to_theta(pipe_ndx, addr, thetas);
+            },
+        }
     }
 }
 
+/// A plain, serializable snapshot of `PipeManagement`'s assignment state.
+///
+/// Captures enough to restore assignments across a restart or to inspect them from
+/// another process; it intentionally excludes the live channel endpoints.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PipeManagementSnapshot {
+    pub addr_to_pipe: HashMap<u32, (usize, usize)>,
+    pub addr_to_theta: HashMap<u32, Vec<f32>>,
+}
+
+impl PipeManagementSnapshot {
+    /// Encodes the snapshot as compact bincode, suitable for on-disk checkpoints.
+    pub fn to_bincode(&self) -> Vec<u8> {
+        bincode::serialize(self).unwrap()
+    }
+
+    /// Decodes a snapshot previously written by `to_bincode`.
+    pub fn from_bincode(bytes: &[u8]) -> PipeManagementSnapshot {
+        bincode::deserialize(bytes).unwrap()
+    }
+
+    /// Encodes the snapshot as CBOR, for interop with external tooling that doesn't
+    /// want to link against our bincode schema.
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        serde_cbor::to_writer(&mut buf, self).unwrap();
+        buf
+    }
+
+    /// Decodes a snapshot previously written by `to_cbor`.
+    pub fn from_cbor(bytes: &[u8]) -> PipeManagementSnapshot {
+        serde_cbor::from_slice(bytes).unwrap()
+    }
+}
+
+/// The serializable mirror of `PipeManagement`'s mutating methods.
+///
+/// A control socket can deserialize one of these and hand it to `apply_command`
+/// without needing any command-specific glue code.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum PipeCommand {
+    /// Mirrors `set_addr_to_theta`.
+    SetAddrTheta(u32, Vec<f32>),
+    /// Mirrors `unset_addr`.
+    UnsetAddr(u32),
+    /// Mirrors `set_pipe_to_theta`.
+    SetPipeTheta(usize, u32, Option<Vec<f32>>),
+}
+
 /// A collection of messages each thread understands.
 pub enum ThreadTxMessage {
     /// Used to send a buffer to be processed to a thread.
     ///
-    /// The first argument is the buffer. The second is the number
-    /// of streams contained in the buffer.
-    Buffer(Vec<u8>, usize),
+    /// The first argument is the shared, read-only sample buffer. The second is the
+    /// number of streams contained in the buffer. The third is the cycle this buffer
+    /// belongs to - `send_buffer_to_all`'s `try_send` drops the buffer for any worker
+    /// whose queue is full, so a slow worker can still be chewing on an older cycle's
+    /// buffer when a newer one is sent; echoing this back on the result channel lets
+    /// the read loop tell a stale result (from a buffer it already moved past) apart
+    /// from a genuine answer to the cycle it's currently collecting.
+    Buffer(Arc<Vec<u8>>, usize, u64),
     /// Used to set a theta to a constant value for a single pipe.
     SetWeights(usize, Vec<f32>),
     /// Used to revert a pipe back to a value that is randomly choosen per buffer process operation.
     UnsetWeights(usize),
+}
+
+/// Messages a worker thread sends back to `PipeManagement` about a pipe's performance.
+pub enum ThreadRxMessage {
+    /// Carries the local (within-thread) pipe index and its latest `PipeReport`.
+    Report(usize, PipeReport),
+}
+
+/// Per-pipe quality feedback measured by a worker thread over the last processed buffer.
+pub struct PipeReport {
+    /// The best SNR seen on this pipe this cycle, or `0.0` if nothing decoded.
+    pub snr: f32,
+    /// How many messages this pipe successfully decoded this cycle.
+    pub decode_count: u32,
+    /// The theta currently in effect for this pipe. When the pipe is in random-search
+    /// mode (no theta assigned by `PipeManagement`) this is the theta of its best
+    /// decode this cycle (empty if nothing decoded), so the manager can see what the
+    /// search has converged towards.
+    pub theta: Vec<f32>,
 }
\ No newline at end of file
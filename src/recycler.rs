@@ -0,0 +1,43 @@
+//! Generic free-list pool for reusable allocations.
+//!
+//! The main read loop and worker threads run at 2 MSPS and were allocating a fresh
+//! sample buffer and a fresh per-cycle `Vec<Message>` every iteration; under load
+//! that churn is exactly what `buffer_time_elapsed_avg` creeping up on `buffer_time`
+//! ("TOO SLOW!!!") is measuring. `Recycler<T>` is a shared free-list: callers `take`
+//! a previously returned value instead of allocating a new one, and `give` it back
+//! once they're done with it, so steady-state operation does no more allocation
+//! after warm-up.
+
+use std::sync::{Arc, Mutex};
+
+pub struct Recycler<T> {
+    free: Arc<Mutex<Vec<T>>>,
+}
+
+impl<T> Recycler<T> {
+    pub fn new() -> Recycler<T> {
+        Recycler { free: Arc::new(Mutex::new(Vec::new())) }
+    }
+
+    /// Takes a previously recycled value off the free-list, or calls `make` to
+    /// allocate a new one if the free-list is empty.
+    pub fn take(&self, make: impl FnOnce() -> T) -> T {
+        match self.free.lock().unwrap().pop() {
+            Some(v) => v,
+            None => make(),
+        }
+    }
+
+    /// Returns a value to the free-list for a future `take` to reuse. The caller is
+    /// responsible for clearing anything that should not be visible to the next
+    /// user (e.g. `Vec::clear`) before handing it back.
+    pub fn give(&self, v: T) {
+        self.free.lock().unwrap().push(v);
+    }
+}
+
+impl<T> Clone for Recycler<T> {
+    fn clone(&self) -> Recycler<T> {
+        Recycler { free: self.free.clone() }
+    }
+}
@@ -1,5 +1,7 @@
 use std::sync::{Arc, Mutex};
 use crate::constants;
+use crate::mvdr;
+use crate::notch;
 use crate::Message;
 use crate::process_result;
 use bytemuck::cast_slice;
@@ -100,7 +102,8 @@ pub fn process_stream_mfloat32(
 ///
 /// This is a loop unrolled version of `process_buffer_single`. This was done
 /// to optimize for performance. The looped version was taking too much CPU
-/// time.
+/// time. The per-sample "scale -> complex-rotate -> sum -> magnitude" kernel
+/// is further vectorized by `combine_x2` when the CPU supports it.
 pub fn process_buffer_single_x2(
     u8_buffer: &[u8],
     thetas_b: f32,
@@ -109,21 +112,53 @@ pub fn process_buffer_single_x2(
     pipe_ndx: usize
 ) -> Vec<ProcessStreamResult> {
     let buffer: &[i16] = cast_slice(u8_buffer);
-    let mut mbuffer: Vec<f32> = Vec::with_capacity(buffer.len() / (2 * 2));
+    let mbuffer = combine_x2(buffer, thetas_b, amplitude_a, amplitude_b);
+
+    process_stream_mfloat32(
+        &mbuffer,
+        &buffer,
+        &vec![thetas_b],
+        &vec![amplitude_a, amplitude_b],
+        2,
+        pipe_ndx
+    )
+}
+
+/// Scales antenna A and B by their amplitudes, rotates B by `thetas_b`, sums
+/// the two, and takes the magnitude of every complex sample in `buffer`.
+///
+/// Dispatches to an SSE2-vectorized kernel that processes 4 samples per
+/// iteration when the CPU supports it (which is effectively always on
+/// x86_64), falling back to the portable scalar loop otherwise. Both paths
+/// agree to within f32 epsilon.
+fn combine_x2(buffer: &[i16], thetas_b: f32, amplitude_a: f32, amplitude_b: f32) -> Vec<f32> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("sse2") {
+            return unsafe { combine_x2_sse2(buffer, thetas_b, amplitude_a, amplitude_b) };
+        }
+    }
+
+    combine_x2_scalar(buffer, thetas_b, amplitude_a, amplitude_b)
+}
+
+fn combine_x2_scalar(buffer: &[i16], thetas_b: f32, amplitude_a: f32, amplitude_b: f32) -> Vec<f32> {
+    let mut mbuffer: Vec<f32> = Vec::with_capacity(buffer.len() / 4);
 
     let bri = thetas_b.cos();
     let brq = thetas_b.sin();
 
     for x in 0..buffer.len() / 4 {
         let chunk = &buffer[x * 4..x * 4 + 4];
-        let ai: f32 =     chunk[0] as f32 / 2049.0 * amplitude_a;
-        let aq: f32 =     chunk[1] as f32 / 2049.0 * amplitude_a;
-        let mut bi: f32 = chunk[2] as f32 / 2049.0 * amplitude_b;
-        let mut bq: f32 = chunk[3] as f32 / 2049.0 * amplitude_b;
-        
-        // Rotate the vectors by the thetas provided.
-        bi = bi * bri - bq * brq;
-        bq = bi * brq + bq * bri;
+        let ai: f32 = chunk[0] as f32 / 2049.0 * amplitude_a;
+        let aq: f32 = chunk[1] as f32 / 2049.0 * amplitude_a;
+        let bi0: f32 = chunk[2] as f32 / 2049.0 * amplitude_b;
+        let bq0: f32 = chunk[3] as f32 / 2049.0 * amplitude_b;
+
+        // Rotate the vectors by the thetas provided, from the original
+        // (unrotated) antenna B samples.
+        let bi = bi0 * bri - bq0 * brq;
+        let bq = bi0 * brq + bq0 * bri;
         // Sum the vectors.
         let ei = ai + bi;
         let eq = aq + bq;
@@ -131,10 +166,130 @@ pub fn process_buffer_single_x2(
         mbuffer.push((ei * ei + eq * eq).sqrt());
     }
 
+    mbuffer
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn combine_x2_sse2(buffer: &[i16], thetas_b: f32, amplitude_a: f32, amplitude_b: f32) -> Vec<f32> {
+    use std::arch::x86_64::*;
+
+    let samples = buffer.len() / 4;
+    let lanes = samples / 4;
+    let mut mbuffer: Vec<f32> = Vec::with_capacity(samples);
+
+    let bri = _mm_set1_ps(thetas_b.cos());
+    let brq = _mm_set1_ps(thetas_b.sin());
+    let amp_a = _mm_set1_ps(amplitude_a);
+    let amp_b = _mm_set1_ps(amplitude_b);
+    let scale = _mm_set1_ps(1.0 / 2049.0);
+
+    let mut ai_lane = [0f32; 4];
+    let mut aq_lane = [0f32; 4];
+    let mut bi_lane = [0f32; 4];
+    let mut bq_lane = [0f32; 4];
+
+    for x in 0..lanes {
+        for lane in 0..4 {
+            let chunk = &buffer[(x * 4 + lane) * 4..(x * 4 + lane) * 4 + 4];
+            ai_lane[lane] = chunk[0] as f32;
+            aq_lane[lane] = chunk[1] as f32;
+            bi_lane[lane] = chunk[2] as f32;
+            bq_lane[lane] = chunk[3] as f32;
+        }
+
+        let ai = _mm_mul_ps(_mm_mul_ps(_mm_loadu_ps(ai_lane.as_ptr()), scale), amp_a);
+        let aq = _mm_mul_ps(_mm_mul_ps(_mm_loadu_ps(aq_lane.as_ptr()), scale), amp_a);
+        let bi0 = _mm_mul_ps(_mm_mul_ps(_mm_loadu_ps(bi_lane.as_ptr()), scale), amp_b);
+        let bq0 = _mm_mul_ps(_mm_mul_ps(_mm_loadu_ps(bq_lane.as_ptr()), scale), amp_b);
+
+        // Rotate antenna B by thetas_b, from the original (unrotated) samples.
+        let bi = _mm_sub_ps(_mm_mul_ps(bi0, bri), _mm_mul_ps(bq0, brq));
+        let bq = _mm_add_ps(_mm_mul_ps(bi0, brq), _mm_mul_ps(bq0, bri));
+
+        let ei = _mm_add_ps(ai, bi);
+        let eq = _mm_add_ps(aq, bq);
+
+        let mag = _mm_sqrt_ps(_mm_add_ps(_mm_mul_ps(ei, ei), _mm_mul_ps(eq, eq)));
+
+        let mut out = [0f32; 4];
+        _mm_storeu_ps(out.as_mut_ptr(), mag);
+        mbuffer.extend_from_slice(&out);
+    }
+
+    // The tail that doesn't fill a full 4-wide lane falls back to the scalar
+    // kernel, using the same corrected rotation.
+    if lanes * 4 < samples {
+        let bri = thetas_b.cos();
+        let brq = thetas_b.sin();
+        for x in lanes * 4..samples {
+            let chunk = &buffer[x * 4..x * 4 + 4];
+            let ai: f32 = chunk[0] as f32 / 2049.0 * amplitude_a;
+            let aq: f32 = chunk[1] as f32 / 2049.0 * amplitude_a;
+            let bi0: f32 = chunk[2] as f32 / 2049.0 * amplitude_b;
+            let bq0: f32 = chunk[3] as f32 / 2049.0 * amplitude_b;
+            let bi = bi0 * bri - bq0 * brq;
+            let bq = bi0 * brq + bq0 * bri;
+            let ei = ai + bi;
+            let eq = aq + bq;
+            mbuffer.push((ei * ei + eq * eq).sqrt());
+        }
+    }
+
+    mbuffer
+}
+
+/// Does a single beamforming operation, the same as `process_buffer_single_x2`, but
+/// combines the antennas with MVDR weights computed from `covariance` instead of a
+/// fixed `theta` rotation.
+///
+/// Every sample is folded into `covariance` first so the spatial statistics keep
+/// adapting as the buffer is scanned. Whenever `covariance` hasn't filled its window
+/// yet, or the resulting matrix is too close to singular to invert, this falls back
+/// to the conventional steered-sum combine `process_buffer_single_x2` uses, so a
+/// cold or ill-conditioned covariance never produces a blank stream.
+pub fn process_buffer_single_x2_mvdr(
+    u8_buffer: &[u8],
+    theta: f32,
+    amplitude_a: f32,
+    amplitude_b: f32,
+    pipe_ndx: usize,
+    covariance: &mut mvdr::Covariance,
+    loading_fraction: f32
+) -> Vec<ProcessStreamResult> {
+    let buffer: &[i16] = cast_slice(u8_buffer);
+    let mut mbuffer: Vec<f32> = Vec::with_capacity(buffer.len() / (2 * 2));
+
+    let bri = theta.cos();
+    let brq = theta.sin();
+
+    for x in 0..buffer.len() / 4 {
+        let chunk = &buffer[x * 4..x * 4 + 4];
+        let ai: f32 = chunk[0] as f32 / 2049.0 * amplitude_a;
+        let aq: f32 = chunk[1] as f32 / 2049.0 * amplitude_a;
+        let bi: f32 = chunk[2] as f32 / 2049.0 * amplitude_b;
+        let bq: f32 = chunk[3] as f32 / 2049.0 * amplitude_b;
+
+        covariance.push((ai, aq), (bi, bq));
+
+        let (ei, eq) = match covariance.mvdr_weights(theta, loading_fraction) {
+            Some(w) => mvdr::apply_weights(w, (ai, aq), (bi, bq)),
+            None => {
+                // Same fallback math as process_buffer_single_x2: steer antenna B by
+                // theta and sum.
+                let rbi = bi * bri - bq * brq;
+                let rbq = bi * brq + bq * bri;
+                (ai + rbi, aq + rbq)
+            },
+        };
+
+        mbuffer.push((ei * ei + eq * eq).sqrt());
+    }
+
     process_stream_mfloat32(
         &mbuffer,
         &buffer,
-        &vec![thetas_b],
+        &vec![theta],
         &vec![amplitude_a, amplitude_b],
         2,
         pipe_ndx
@@ -145,7 +300,8 @@ pub fn process_buffer_single_x2(
 ///
 /// This is a loop unrolled version of `process_buffer_single`. This was done
 /// to optimize for performance. The looped version was taking too much CPU
-/// time.
+/// time. The per-sample "scale -> complex-rotate -> sum -> magnitude" kernel
+/// is further vectorized by `combine_x4` when the CPU supports it.
 pub fn process_buffer_single_x4(
     u8_buffer: &[u8],
     thetas_b: f32,
@@ -158,7 +314,60 @@ pub fn process_buffer_single_x4(
     pipe_ndx: usize
 ) -> Vec<ProcessStreamResult> {
     let buffer: &[i16] = cast_slice(u8_buffer);
-    let mut mbuffer: Vec<f32> = Vec::with_capacity(buffer.len() / (4 * 2));
+    let mbuffer = combine_x4(
+        buffer,
+        thetas_b, thetas_c, thetas_d,
+        amplitude_a, amplitude_b, amplitude_c, amplitude_d
+    );
+
+    process_stream_mfloat32(
+        &mbuffer,
+        &buffer,
+        &vec![thetas_b, thetas_c, thetas_d],
+        &vec![amplitude_a, amplitude_b, amplitude_c, amplitude_d],
+        4,
+        pipe_ndx
+    )
+}
+
+/// Scales the four antennas by their amplitudes, rotates B/C/D by their
+/// respective thetas, sums all four, and takes the magnitude of every
+/// complex sample in `buffer`.
+///
+/// Dispatches to an SSE2-vectorized kernel that processes 4 samples per
+/// iteration when the CPU supports it, falling back to the portable scalar
+/// loop otherwise. Both paths agree to within f32 epsilon.
+fn combine_x4(
+    buffer: &[i16],
+    thetas_b: f32, thetas_c: f32, thetas_d: f32,
+    amplitude_a: f32, amplitude_b: f32, amplitude_c: f32, amplitude_d: f32
+) -> Vec<f32> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("sse2") {
+            return unsafe {
+                combine_x4_sse2(
+                    buffer,
+                    thetas_b, thetas_c, thetas_d,
+                    amplitude_a, amplitude_b, amplitude_c, amplitude_d
+                )
+            };
+        }
+    }
+
+    combine_x4_scalar(
+        buffer,
+        thetas_b, thetas_c, thetas_d,
+        amplitude_a, amplitude_b, amplitude_c, amplitude_d
+    )
+}
+
+fn combine_x4_scalar(
+    buffer: &[i16],
+    thetas_b: f32, thetas_c: f32, thetas_d: f32,
+    amplitude_a: f32, amplitude_b: f32, amplitude_c: f32, amplitude_d: f32
+) -> Vec<f32> {
+    let mut mbuffer: Vec<f32> = Vec::with_capacity(buffer.len() / 8);
 
     let bri = thetas_b.cos();
     let brq = thetas_b.sin();
@@ -169,22 +378,23 @@ pub fn process_buffer_single_x4(
 
     for x in 0..buffer.len() / 8 {
         let chunk = &buffer[x * 8..x * 8 + 8];
-        let ai: f32 =     chunk[0] as f32 / 2049.0 * amplitude_a;
-        let aq: f32 =     chunk[1] as f32 / 2049.0 * amplitude_a;
-        let mut bi: f32 = chunk[2] as f32 / 2049.0 * amplitude_b;
-        let mut bq: f32 = chunk[3] as f32 / 2049.0 * amplitude_b;
-        let mut ci: f32 = chunk[4] as f32 / 2049.0 * amplitude_c;
-        let mut cq: f32 = chunk[5] as f32 / 2049.0 * amplitude_c;
-        let mut di: f32 = chunk[6] as f32 / 2049.0 * amplitude_d;
-        let mut dq: f32 = chunk[7] as f32 / 2049.0 * amplitude_d;
-        
-        // Rotate the vectors by the thetas provided.
-        bi = bi * bri - bq * brq;
-        bq = bi * brq + bq * bri;
-        ci = ci * cri - cq * crq;
-        cq = ci * crq + cq * cri;
-        di = di * dri - dq * drq;
-        dq = di * drq + dq * dri;
+        let ai: f32 = chunk[0] as f32 / 2049.0 * amplitude_a;
+        let aq: f32 = chunk[1] as f32 / 2049.0 * amplitude_a;
+        let bi0: f32 = chunk[2] as f32 / 2049.0 * amplitude_b;
+        let bq0: f32 = chunk[3] as f32 / 2049.0 * amplitude_b;
+        let ci0: f32 = chunk[4] as f32 / 2049.0 * amplitude_c;
+        let cq0: f32 = chunk[5] as f32 / 2049.0 * amplitude_c;
+        let di0: f32 = chunk[6] as f32 / 2049.0 * amplitude_d;
+        let dq0: f32 = chunk[7] as f32 / 2049.0 * amplitude_d;
+
+        // Rotate the vectors by the thetas provided, from the original
+        // (unrotated) antenna samples.
+        let bi = bi0 * bri - bq0 * brq;
+        let bq = bi0 * brq + bq0 * bri;
+        let ci = ci0 * cri - cq0 * crq;
+        let cq = ci0 * crq + cq0 * cri;
+        let di = di0 * dri - dq0 * drq;
+        let dq = di0 * drq + dq0 * dri;
         // Sum the vectors.
         let ei = ai + bi + ci + di;
         let eq = aq + bq + cq + dq;
@@ -192,14 +402,119 @@ pub fn process_buffer_single_x4(
         mbuffer.push((ei * ei + eq * eq).sqrt());
     }
 
-    process_stream_mfloat32(
-        &mbuffer,
-        &buffer,
-        &vec![thetas_b, thetas_c, thetas_d],
-        &vec![amplitude_a, amplitude_b, amplitude_c, amplitude_d],
-        4,
-        pipe_ndx
-    )
+    mbuffer
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn combine_x4_sse2(
+    buffer: &[i16],
+    thetas_b: f32, thetas_c: f32, thetas_d: f32,
+    amplitude_a: f32, amplitude_b: f32, amplitude_c: f32, amplitude_d: f32
+) -> Vec<f32> {
+    use std::arch::x86_64::*;
+
+    let samples = buffer.len() / 8;
+    let lanes = samples / 4;
+    let mut mbuffer: Vec<f32> = Vec::with_capacity(samples);
+
+    let bri = _mm_set1_ps(thetas_b.cos());
+    let brq = _mm_set1_ps(thetas_b.sin());
+    let cri = _mm_set1_ps(thetas_c.cos());
+    let crq = _mm_set1_ps(thetas_c.sin());
+    let dri = _mm_set1_ps(thetas_d.cos());
+    let drq = _mm_set1_ps(thetas_d.sin());
+    let amp_a = _mm_set1_ps(amplitude_a);
+    let amp_b = _mm_set1_ps(amplitude_b);
+    let amp_c = _mm_set1_ps(amplitude_c);
+    let amp_d = _mm_set1_ps(amplitude_d);
+    let scale = _mm_set1_ps(1.0 / 2049.0);
+
+    let mut ai_lane = [0f32; 4];
+    let mut aq_lane = [0f32; 4];
+    let mut bi_lane = [0f32; 4];
+    let mut bq_lane = [0f32; 4];
+    let mut ci_lane = [0f32; 4];
+    let mut cq_lane = [0f32; 4];
+    let mut di_lane = [0f32; 4];
+    let mut dq_lane = [0f32; 4];
+
+    for x in 0..lanes {
+        for lane in 0..4 {
+            let chunk = &buffer[(x * 4 + lane) * 8..(x * 4 + lane) * 8 + 8];
+            ai_lane[lane] = chunk[0] as f32;
+            aq_lane[lane] = chunk[1] as f32;
+            bi_lane[lane] = chunk[2] as f32;
+            bq_lane[lane] = chunk[3] as f32;
+            ci_lane[lane] = chunk[4] as f32;
+            cq_lane[lane] = chunk[5] as f32;
+            di_lane[lane] = chunk[6] as f32;
+            dq_lane[lane] = chunk[7] as f32;
+        }
+
+        let ai = _mm_mul_ps(_mm_mul_ps(_mm_loadu_ps(ai_lane.as_ptr()), scale), amp_a);
+        let aq = _mm_mul_ps(_mm_mul_ps(_mm_loadu_ps(aq_lane.as_ptr()), scale), amp_a);
+        let bi0 = _mm_mul_ps(_mm_mul_ps(_mm_loadu_ps(bi_lane.as_ptr()), scale), amp_b);
+        let bq0 = _mm_mul_ps(_mm_mul_ps(_mm_loadu_ps(bq_lane.as_ptr()), scale), amp_b);
+        let ci0 = _mm_mul_ps(_mm_mul_ps(_mm_loadu_ps(ci_lane.as_ptr()), scale), amp_c);
+        let cq0 = _mm_mul_ps(_mm_mul_ps(_mm_loadu_ps(cq_lane.as_ptr()), scale), amp_c);
+        let di0 = _mm_mul_ps(_mm_mul_ps(_mm_loadu_ps(di_lane.as_ptr()), scale), amp_d);
+        let dq0 = _mm_mul_ps(_mm_mul_ps(_mm_loadu_ps(dq_lane.as_ptr()), scale), amp_d);
+
+        // Rotate antennas B/C/D by their thetas, from the original
+        // (unrotated) samples.
+        let bi = _mm_sub_ps(_mm_mul_ps(bi0, bri), _mm_mul_ps(bq0, brq));
+        let bq = _mm_add_ps(_mm_mul_ps(bi0, brq), _mm_mul_ps(bq0, bri));
+        let ci = _mm_sub_ps(_mm_mul_ps(ci0, cri), _mm_mul_ps(cq0, crq));
+        let cq = _mm_add_ps(_mm_mul_ps(ci0, crq), _mm_mul_ps(cq0, cri));
+        let di = _mm_sub_ps(_mm_mul_ps(di0, dri), _mm_mul_ps(dq0, drq));
+        let dq = _mm_add_ps(_mm_mul_ps(di0, drq), _mm_mul_ps(dq0, dri));
+
+        let ei = _mm_add_ps(_mm_add_ps(ai, bi), _mm_add_ps(ci, di));
+        let eq = _mm_add_ps(_mm_add_ps(aq, bq), _mm_add_ps(cq, dq));
+
+        let mag = _mm_sqrt_ps(_mm_add_ps(_mm_mul_ps(ei, ei), _mm_mul_ps(eq, eq)));
+
+        let mut out = [0f32; 4];
+        _mm_storeu_ps(out.as_mut_ptr(), mag);
+        mbuffer.extend_from_slice(&out);
+    }
+
+    // The tail that doesn't fill a full 4-wide lane falls back to the scalar
+    // kernel, using the same corrected rotation.
+    if lanes * 4 < samples {
+        let bri = thetas_b.cos();
+        let brq = thetas_b.sin();
+        let cri = thetas_c.cos();
+        let crq = thetas_c.sin();
+        let dri = thetas_d.cos();
+        let drq = thetas_d.sin();
+
+        for x in lanes * 4..samples {
+            let chunk = &buffer[x * 8..x * 8 + 8];
+            let ai: f32 = chunk[0] as f32 / 2049.0 * amplitude_a;
+            let aq: f32 = chunk[1] as f32 / 2049.0 * amplitude_a;
+            let bi0: f32 = chunk[2] as f32 / 2049.0 * amplitude_b;
+            let bq0: f32 = chunk[3] as f32 / 2049.0 * amplitude_b;
+            let ci0: f32 = chunk[4] as f32 / 2049.0 * amplitude_c;
+            let cq0: f32 = chunk[5] as f32 / 2049.0 * amplitude_c;
+            let di0: f32 = chunk[6] as f32 / 2049.0 * amplitude_d;
+            let dq0: f32 = chunk[7] as f32 / 2049.0 * amplitude_d;
+
+            let bi = bi0 * bri - bq0 * brq;
+            let bq = bi0 * brq + bq0 * bri;
+            let ci = ci0 * cri - cq0 * crq;
+            let cq = ci0 * crq + cq0 * cri;
+            let di = di0 * dri - dq0 * drq;
+            let dq = di0 * drq + dq0 * dri;
+
+            let ei = ai + bi + ci + di;
+            let eq = aq + bq + cq + dq;
+            mbuffer.push((ei * ei + eq * eq).sqrt());
+        }
+    }
+
+    mbuffer
 }
 
 /// Does a single beamforming operation on the interleaved two antenna stream.
@@ -217,8 +532,9 @@ pub fn process_buffer_single(
     thetas: &[f32],
     amplitudes: &[f32],
     streams: usize,
-    pipe_ndx: usize
-) -> Vec<ProcessStreamResult> {    
+    pipe_ndx: usize,
+    notch: Option<&mut notch::AutoNotch>
+) -> Vec<ProcessStreamResult> {
     if amplitudes.len() != streams {
         panic!("The number of amplitudes passed as part of Vec<f32> should be equal to the number of streams.")
     }
@@ -259,11 +575,12 @@ pub fn process_buffer_single(
     }
 
     let mul = streams * 2;
+    let mut notch = notch;
 
     for x in 0..buffer.len() / mul {
         let mut ai: f32 = buffer[x * mul + 0] as f32 / 2049.0 * amplitudes[0];
         let mut aq: f32 = buffer[x * mul + 1] as f32 / 2049.0 * amplitudes[0];
-        
+
         for si in 1..streams {
             let (ri, rq) = riq[si - 1];
             let bi = buffer[x * mul + si * 2 + 0] as f32 / 2049.0 * amplitudes[si];
@@ -279,6 +596,15 @@ pub fn process_buffer_single(
             aq = bi * rq + bq * ri + aq;
         }
 
+        // Strip any tracked narrowband interferers before the magnitude is taken,
+        // so a strong CW/narrowband emitter doesn't raise the noise floor seen by
+        // the preamble test.
+        if let Some(notch) = notch.as_deref_mut() {
+            let (ei, eq) = notch.process((ai, aq));
+            ai = ei;
+            aq = eq;
+        }
+
         mbuffer.push((ai * ai + aq * aq).sqrt());
     }
 
@@ -291,15 +617,31 @@ pub fn process_buffer_single(
 /// look for messages. It also supports setting a custom set of thetas for
 /// each pipe/cycle. At the end, it translate the raw bytes into a message
 /// format.
+///
+/// `pipe_covariance` holds one `mvdr::Covariance` per pipe that has MVDR enabled
+/// (an empty `Vec` disables it for all pipes); when present for a pipe it is used
+/// instead of the fixed-theta conventional combine for that pipe's 2-antenna case.
+///
+/// Decoded messages are pushed into `out` rather than returned, so callers can hand
+/// in a `Vec<Message>` drawn from a `recycler::Recycler` and keep its allocation
+/// across cycles instead of getting a freshly allocated one every call.
 pub fn process_buffer(
     u8_buffer: &[u8],
-    bit_error_table: &HashMap<u32, u16>,
+    bit_error_table: &HashMap<u32, u32>,
     pipe_theta: &Vec<Option<Vec<f32>>>,
     pipe_amps: &Vec<Option<Vec<f32>>>,
     streams: usize,
     seen: &Arc<Mutex<HashMap<u32, Instant>>>,
-    base_pipe_ndx: usize
-) -> Vec<Message> {
+    base_pipe_ndx: usize,
+    randomize_amplitudes: bool,
+    pipe_covariance: &mut Vec<mvdr::Covariance>,
+    mvdr_loading: f32,
+    aggressive_crc_fix: bool,
+    pipe_notch: &mut Vec<Option<notch::AutoNotch>>,
+    out: &mut Vec<Message>
+) {
+    out.clear();
+
     let buffer: &[i16] = cast_slice(u8_buffer);
     let mut mbuffer: Vec<f32> = Vec::with_capacity(buffer.len() / 4);
     let mut rng = rand::thread_rng();
@@ -327,7 +669,7 @@ pub fn process_buffer(
                 }
 
                 for i in 0..streams {
-                    amplitudes[i] = 1.0; //rng.r#gen::<f32>();
+                    amplitudes[i] = if randomize_amplitudes { rng.r#gen::<f32>() } else { 1.0 };
                 }
             },
             Some(thetas_other) => {
@@ -359,7 +701,23 @@ pub fn process_buffer(
             },
         };
 
-        let results = process_buffer_single(u8_buffer, &thetas, &amplitudes, streams, base_pipe_ndx + pipe_ndx);
+        // A non-empty `pipe_covariance` means MVDR is enabled; it only backs the
+        // 2-antenna case for now, so 4+ antenna setups always take the conventional
+        // path below regardless.
+        let results = if streams == 2 && pipe_ndx < pipe_covariance.len() {
+            process_buffer_single_x2_mvdr(
+                u8_buffer,
+                thetas[0],
+                amplitudes[0],
+                amplitudes[1],
+                base_pipe_ndx + pipe_ndx,
+                &mut pipe_covariance[pipe_ndx],
+                mvdr_loading
+            )
+        } else {
+            let notch = pipe_notch.get_mut(pipe_ndx).and_then(|n| n.as_mut());
+            process_buffer_single(u8_buffer, &thetas, &amplitudes, streams, base_pipe_ndx + pipe_ndx, notch)
+        };
 
         for result in results {
             match hm.get(&result.ndx) {
@@ -377,14 +735,10 @@ pub fn process_buffer(
         mbuffer.clear();
     }
 
-    let mut out: Vec<Message> = Vec::new();
-
     for (_, result) in hm {
-        match process_result(result, bit_error_table, seen) {
+        match process_result(result, bit_error_table, seen, aggressive_crc_fix) {
             Ok(message) => out.push(message),
             Err(_) => (),
         }
     }
-
-    out
 }